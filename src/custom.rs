@@ -0,0 +1,165 @@
+use std::sync::{Arc, RwLock};
+
+use crate::Operation;
+
+/// A user-defined operation, usable in graphs alongside the built-in ones once registered
+/// with [`register_op`]
+pub trait CustomOp: Send + Sync {
+    /// Label shown when the graph is rendered
+    fn name(&self) -> String;
+
+    /// Number of inputs the operation takes
+    fn arity(&self) -> usize;
+
+    /// Evaluate the operation on its inputs
+    fn forward(&self, inputs: &[f64]) -> f64;
+
+    /// Gradient with respect to each input, given the gradient `grad` flowing into the output.
+    /// Forward mode calls this with `grad` of 1 and scales the result, so it should be linear in `grad`.
+    fn backward(&self, inputs: &[f64], output: f64, grad: f64) -> Vec<f64>;
+}
+
+/// Handle to a registered [`CustomOp`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CustomOpId(usize);
+
+static REGISTRY: RwLock<Vec<Arc<dyn CustomOp>>> = RwLock::new(Vec::new());
+
+/// Register a custom operation, returning the `Operation` to build it with,
+/// e.g. through [`Value::apply`](crate::Value::apply)
+pub fn register_op<T: CustomOp + 'static>(op: T) -> Operation {
+    assert!(op.arity() > 0, "custom operation {} takes no inputs", op.name());
+    let mut registry = REGISTRY.write().unwrap();
+    registry.push(Arc::new(op));
+    Operation::Custom(CustomOpId(registry.len() - 1))
+}
+
+pub(crate) fn lookup(id: CustomOpId) -> Arc<dyn CustomOp> {
+    REGISTRY.read().unwrap()[id.0].clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{gradcheck, hessian, jvp, test_util::assert_close, AutogradError, Arena, Dual, GradcheckOptions, Value};
+    use super::*;
+
+    struct Hypot;
+
+    impl CustomOp for Hypot {
+        fn name(&self) -> String {
+            "hypot".to_string()
+        }
+
+        fn arity(&self) -> usize {
+            2
+        }
+
+        fn forward(&self, inputs: &[f64]) -> f64 {
+            inputs[0].hypot(inputs[1])
+        }
+
+        fn backward(&self, inputs: &[f64], output: f64, grad: f64) -> Vec<f64> {
+            vec![grad * inputs[0] / output, grad * inputs[1] / output]
+        }
+    }
+
+    #[test]
+    fn custom_op_backward() {
+        let hypot = register_op(Hypot);
+        let arena = Arena::new();
+        let x = arena.value(3.0).label("x");
+        let y = arena.value(4.0).label("y");
+        let out = Value::apply(hypot, &[x * 2.0, y]).tanh();
+        out.backward();
+
+        let h = 6.0f64.hypot(4.0);
+        let dtanh = 1.0 - h.tanh().powi(2);
+        assert_close(out.value(), h.tanh());
+        assert_close(x.grad(), dtanh * 2.0 * 6.0 / h);
+        assert_close(y.grad(), dtanh * 4.0 / h);
+        assert!(gradcheck(out, GradcheckOptions::default()).passed());
+        assert!(out.to_dot().contains("hypot"));
+    }
+
+    struct Square;
+
+    impl CustomOp for Square {
+        fn name(&self) -> String {
+            "square".to_string()
+        }
+
+        fn arity(&self) -> usize {
+            1
+        }
+
+        fn forward(&self, inputs: &[f64]) -> f64 {
+            inputs[0] * inputs[0]
+        }
+
+        fn backward(&self, inputs: &[f64], _output: f64, grad: f64) -> Vec<f64> {
+            vec![2.0 * inputs[0] * grad]
+        }
+    }
+
+    #[test]
+    fn custom_op_second_order() {
+        let square = register_op(Square);
+        // d/dx square(x)^2 = 2 square(x) * 2x, where the custom 2x is a constant, so the
+        // second derivative keeps only the outer term: 2 * (2x)^2 = 32 at x = 2
+        let h = hessian(|x| Value::apply(square, &[x[0]]).pow(2.0), &[2.0]);
+        assert_close(h[0][0], 32.0);
+    }
+
+    #[test]
+    fn custom_op_forward_mode() {
+        let hypot = register_op(Hypot);
+        let (outputs, tangents) = jvp(|x| vec![Dual::apply(hypot, &[x[0], x[1]])], &[3.0, 4.0], &[1.0, 0.0]);
+        assert_close(outputs[0], 5.0);
+        assert_close(tangents[0], 0.6);
+    }
+
+    // Forgets the gradient of its second input
+    struct BadProduct;
+
+    impl CustomOp for BadProduct {
+        fn name(&self) -> String {
+            "bad_product".to_string()
+        }
+
+        fn arity(&self) -> usize {
+            2
+        }
+
+        fn forward(&self, inputs: &[f64]) -> f64 {
+            inputs[0] * inputs[1]
+        }
+
+        fn backward(&self, inputs: &[f64], _output: f64, grad: f64) -> Vec<f64> {
+            vec![grad * inputs[1]]
+        }
+    }
+
+    #[test]
+    fn custom_op_gradient_count_mismatch() {
+        let bad = register_op(BadProduct);
+        let arena = Arena::new();
+        let out = Value::apply(bad, &[arena.value(2.0), arena.value(3.0)]);
+        assert!(matches!(out.try_backward(), Err(AutogradError::GradientCountMismatch { expected: 2, found: 1, .. })));
+    }
+
+    #[test]
+    #[should_panic(expected = "bad_product returned 1 gradients, expected 2")]
+    fn custom_op_gradient_count_panics_in_backward() {
+        let bad = register_op(BadProduct);
+        let arena = Arena::new();
+        Value::apply(bad, &[arena.value(2.0), arena.value(3.0)]).backward();
+    }
+
+    #[test]
+    fn custom_op_arity_mismatch() {
+        let hypot = register_op(Hypot);
+        let arena = Arena::new();
+        let out = Value::apply(hypot, &[arena.value(1.0)]);
+        assert!(matches!(out.try_backward(), Err(AutogradError::ArityMismatch { expected: 2, found: 1, .. })));
+    }
+}
@@ -0,0 +1,196 @@
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+use crate::Operation;
+
+/// Dual number for forward-mode differentiation, carrying a value and its directional derivative
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Dual {
+    pub value: f64,
+    pub tangent: f64,
+}
+
+impl Dual {
+    pub fn new(value: f64, tangent: f64) -> Self {
+        Self { value, tangent }
+    }
+
+    /// A dual with no tangent
+    pub fn constant(value: f64) -> Self {
+        Self::new(value, 0.0)
+    }
+
+    /// A dual seeded with a unit tangent, to differentiate with respect to it
+    pub fn variable(value: f64) -> Self {
+        Self::new(value, 1.0)
+    }
+
+    /// Apply any operation, built-in or registered with [`register_op`](crate::register_op), to `inputs`
+    pub fn apply(operation: Operation, inputs: &[Dual]) -> Self {
+        assert_eq!(inputs.len(), operation.arity(), "wrong number of inputs to {}", operation);
+        assert!(!matches!(operation, Operation::CustomGrad(_)), "custom_grad has no forward-mode rule");
+        Dual::from_op(inputs, operation)
+    }
+
+    fn from_op(inputs: &[Dual], operation: Operation) -> Self {
+        let values: Vec<f64> = inputs.iter().map(|x| x.value).collect();
+        let value = operation.apply(&values);
+        let tangent = operation.derivatives(&values, value).iter().zip(inputs).enumerate()
+            // Skip inputs without a tangent so infinite derivatives don't turn into NaN
+            .map(|(slot, (derivative, x))| {
+                if x.tangent == 0.0 || !operation.differentiable(slot) { 0.0 } else { derivative * x.tangent }
+            })
+            .sum();
+        Self::new(value, tangent)
+    }
+}
+
+// Operators
+macro_rules! binary_op {
+    ($op:ident, $method:ident, $operation:expr) => {
+        impl $op for Dual {
+            type Output = Dual;
+
+            fn $method(self, rhs: Dual) -> Self::Output {
+                Dual::from_op(&[self, rhs], $operation)
+            }
+        }
+
+        impl $op<f64> for Dual {
+            type Output = Dual;
+
+            fn $method(self, rhs: f64) -> Self::Output {
+                self.$method(Dual::constant(rhs))
+            }
+        }
+
+        impl $op<Dual> for f64 {
+            type Output = Dual;
+
+            fn $method(self, rhs: Dual) -> Self::Output {
+                Dual::constant(self).$method(rhs)
+            }
+        }
+    };
+}
+
+binary_op!(Add, add, Operation::Add);
+binary_op!(Sub, sub, Operation::Sub);
+binary_op!(Mul, mul, Operation::Mul);
+binary_op!(Div, div, Operation::Div);
+
+impl Neg for Dual {
+    type Output = Dual;
+
+    fn neg(self) -> Self::Output {
+        Dual::from_op(&[self], Operation::Neg)
+    }
+}
+
+impl Dual {
+    pub fn pow(self, exponent: f64) -> Dual {
+        Dual::from_op(&[self], Operation::Pow(exponent))
+    }
+
+    pub fn exp(self) -> Dual {
+        Dual::from_op(&[self], Operation::Exp)
+    }
+
+    pub fn ln(self) -> Dual {
+        Dual::from_op(&[self], Operation::Ln)
+    }
+
+    pub fn sqrt(self) -> Dual {
+        Dual::from_op(&[self], Operation::Sqrt)
+    }
+
+    pub fn abs(self) -> Dual {
+        Dual::from_op(&[self], Operation::Abs)
+    }
+
+    pub fn tanh(self) -> Dual {
+        Dual::from_op(&[self], Operation::Tanh)
+    }
+
+    pub fn relu(self) -> Dual {
+        Dual::from_op(&[self], Operation::Relu)
+    }
+
+    pub fn leaky_relu(self, slope: f64) -> Dual {
+        Dual::from_op(&[self], Operation::LeakyRelu(slope))
+    }
+
+    pub fn sigmoid(self) -> Dual {
+        Dual::from_op(&[self], Operation::Sigmoid)
+    }
+
+    /// GELU using the tanh approximation
+    pub fn gelu(self) -> Dual {
+        Dual::from_op(&[self], Operation::Gelu)
+    }
+
+    pub fn softplus(self) -> Dual {
+        Dual::from_op(&[self], Operation::Softplus)
+    }
+
+    pub fn elu(self, alpha: f64) -> Dual {
+        Dual::from_op(&[self], Operation::Elu(alpha))
+    }
+
+    pub fn silu(self) -> Dual {
+        Dual::from_op(&[self], Operation::Silu)
+    }
+
+    /// The same value with no tangent
+    pub fn detach(self) -> Dual {
+        Dual::from_op(&[self], Operation::Detach)
+    }
+}
+
+/// Jacobian-vector product: evaluate `f` at `x` and its directional derivative along `v`.
+/// Returns the outputs and their tangents.
+pub fn jvp<F>(f: F, x: &[f64], v: &[f64]) -> (Vec<f64>, Vec<f64>)
+where F: Fn(&[Dual]) -> Vec<Dual> {
+    assert_eq!(x.len(), v.len(), "point and direction differ in length");
+    let inputs: Vec<Dual> = x.iter().zip(v).map(|(x, v)| Dual::new(*x, *v)).collect();
+    f(&inputs).into_iter().map(|y| (y.value, y.tangent)).unzip()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_util::assert_close, Arena, Scalar};
+
+    // Written once, differentiated both ways
+    fn model<S: Scalar>(x: &[S]) -> Vec<S> {
+        let h = (x[0] * x[1] + 1.0).tanh();
+        vec![
+            h * x[0] - x[1].exp() / 2.0,
+            (x[0].pow(2.0) + x[1].sigmoid()).ln() + x[0].gelu().relu(),
+            -x[1].softplus() * x[0].silu().elu(1.0) + x[1].abs().sqrt() - x[0].leaky_relu(0.1),
+        ]
+    }
+
+    #[test]
+    fn forward_matches_reverse() {
+        let x = [0.7, -0.3];
+        let v = [0.4, 1.5];
+        let (outputs, tangents) = jvp(model, &x, &v);
+
+        for (i, tangent) in tangents.iter().enumerate() {
+            let arena = Arena::new();
+            let inputs = [arena.value(x[0]), arena.value(x[1])];
+            let output = model(&inputs)[i];
+            output.backward();
+            assert_close(output.value(), outputs[i]);
+            assert_close(*tangent, inputs[0].grad() * v[0] + inputs[1].grad() * v[1]);
+        }
+        assert_eq!(outputs, model(&x));
+    }
+
+    #[test]
+    fn constants_carry_no_tangent() {
+        let x = Dual::variable(3.0);
+        let y = 2.0 * x + Dual::constant(0.0).ln() * 0.0 - 1.0 / x;
+        assert_close(y.tangent, 2.0 + 1.0 / 9.0);
+    }
+}
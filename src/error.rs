@@ -0,0 +1,43 @@
+use std::{error::Error, fmt::Display};
+
+use crate::Operation;
+
+/// Problems found in a computation graph, reported instead of panicking
+#[derive(Debug, Clone, PartialEq)]
+pub enum AutogradError {
+    /// An operation node has the wrong number of inputs
+    ArityMismatch { id: usize, label: String, operation: Operation, expected: usize, found: usize },
+    /// A custom operation or gradient override returned the wrong number of gradients
+    GradientCountMismatch { id: usize, label: String, operation: Operation, expected: usize, found: usize },
+    /// A node has inputs but no operation to combine them
+    MissingOperation { id: usize, label: String },
+    /// A node feeds into itself
+    Cycle { id: usize, label: String },
+    /// The value refers to a node dropped by `Arena::truncate`
+    Dangling { id: usize },
+    NonFiniteValue { id: usize, label: String, value: f64 },
+    NonFiniteGrad { id: usize, label: String, grad: f64 },
+}
+
+impl Display for AutogradError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AutogradError::ArityMismatch { id, label, operation, expected, found } =>
+                write!(f, "node {} ({:?}): {} expects {} inputs, found {}", id, label, operation, expected, found),
+            AutogradError::GradientCountMismatch { id, label, operation, expected, found } =>
+                write!(f, "node {} ({:?}): {} returned {} gradients, expected {}", id, label, operation, found, expected),
+            AutogradError::MissingOperation { id, label } =>
+                write!(f, "node {} ({:?}) has inputs but no operation", id, label),
+            AutogradError::Cycle { id, label } =>
+                write!(f, "node {} ({:?}) is part of a cycle", id, label),
+            AutogradError::Dangling { id } =>
+                write!(f, "node {} no longer exists in its arena", id),
+            AutogradError::NonFiniteValue { id, label, value } =>
+                write!(f, "node {} ({:?}) has non-finite data {}", id, label, value),
+            AutogradError::NonFiniteGrad { id, label, grad } =>
+                write!(f, "node {} ({:?}) has non-finite grad {}", id, label, grad),
+        }
+    }
+}
+
+impl Error for AutogradError {}
@@ -0,0 +1,147 @@
+use std::fmt::Display;
+
+use crate::Value;
+
+/// Settings for [`gradcheck`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GradcheckOptions {
+    /// Step for the central differences
+    pub eps: f64,
+    pub atol: f64,
+    pub rtol: f64,
+}
+
+impl Default for GradcheckOptions {
+    fn default() -> Self {
+        Self { eps: 1e-6, atol: 1e-5, rtol: 1e-3 }
+    }
+}
+
+/// Analytic and numeric gradient of the output with respect to one leaf
+#[derive(Debug, Clone)]
+pub struct LeafCheck<'a> {
+    pub leaf: Value<'a>,
+    pub analytic: f64,
+    pub numeric: f64,
+    pub passed: bool,
+}
+
+impl <'a>LeafCheck<'a> {
+    pub fn abs_error(&self) -> f64 {
+        (self.analytic - self.numeric).abs()
+    }
+
+    pub fn rel_error(&self) -> f64 {
+        self.abs_error() / self.numeric.abs().max(f64::EPSILON)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GradcheckReport<'a> {
+    pub leaves: Vec<LeafCheck<'a>>,
+}
+
+impl <'a>GradcheckReport<'a> {
+    pub fn passed(&self) -> bool {
+        self.leaves.iter().all(|leaf| leaf.passed)
+    }
+
+    pub fn failures(&self) -> Vec<&LeafCheck<'a>> {
+        self.leaves.iter().filter(|leaf| !leaf.passed).collect()
+    }
+}
+
+impl <'a>Display for GradcheckReport<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for check in &self.leaves {
+            writeln!(
+                f,
+                "{} {} | analytic: {} | numeric: {} | abs error: {:.2e} | rel error: {:.2e}",
+                if check.passed { "ok  " } else { "FAIL" },
+                check.leaf.get_label(),
+                check.analytic,
+                check.numeric,
+                check.abs_error(),
+                check.rel_error(),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Compare the gradients from `backward` against central differences for every leaf feeding `output`
+/// that requires grad.
+/// A leaf passes when `|analytic - numeric| <= atol + rtol * |numeric|`.
+pub fn gradcheck<'a>(output: Value<'a>, options: GradcheckOptions) -> GradcheckReport<'a> {
+    let nodes = output.topological_order();
+    for node in &nodes {
+        node.set_grad(0.0);
+    }
+    output.backward();
+
+    let leaves = nodes.into_iter()
+        .filter(|node| node.operation().is_none() && node.requires_grad())
+        .map(|leaf| {
+            let original = leaf.value();
+            let evaluate = |value: f64| {
+                leaf.set_value(value);
+                output.forward();
+                output.value()
+            };
+            let numeric = (evaluate(original + options.eps) - evaluate(original - options.eps)) / (2.0 * options.eps);
+            evaluate(original);
+
+            let analytic = leaf.grad();
+            LeafCheck {
+                leaf,
+                analytic,
+                numeric,
+                passed: (analytic - numeric).abs() <= options.atol + options.rtol * numeric.abs(),
+            }
+        })
+        .collect();
+    GradcheckReport { leaves }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Arena;
+
+    #[test]
+    fn every_operation_passes() {
+        let arena = Arena::new();
+        let x = arena.value(0.7).label("x");
+        let y = arena.value(-1.3).label("y");
+        let out = (x * y).tanh() + (x / y).exp() - (-x).sigmoid() * y.pow(3.0)
+            + (x - y).sqrt().ln() + y.abs() * x.relu() + y.leaky_relu(0.1) * x.gelu()
+            + y.softplus() - x.elu(0.5) * y.elu(0.5) + y.silu();
+        let report = gradcheck(out, GradcheckOptions::default());
+
+        assert!(report.passed(), "{}", report);
+        assert_eq!(report.leaves.len(), 2);
+    }
+
+    #[test]
+    fn restores_values() {
+        let arena = Arena::new();
+        let x = arena.value(2.0);
+        let out = x.pow(2.0);
+        gradcheck(out, GradcheckOptions::default());
+
+        assert_eq!(x.value(), 2.0);
+        assert_eq!(out.value(), 4.0);
+    }
+
+    #[test]
+    fn flags_kinks() {
+        // The subgradient of relu at 0 is 0, but central differences see 0.5
+        let arena = Arena::new();
+        let x = arena.value(0.0).label("x");
+        let report = gradcheck(x.relu() * 2.0, GradcheckOptions::default());
+
+        assert!(!report.passed());
+        assert_eq!(report.failures()[0].leaf, x);
+        assert!(report.to_string().starts_with("FAIL x"));
+    }
+}
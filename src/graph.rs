@@ -0,0 +1,619 @@
+use std::{collections::HashMap, fmt::{Display, Write}, io, path::Path};
+
+use petgraph::{Graph, Directed, Direction, algo::toposort, graph::NodeIndex, dot::Dot, visit::EdgeRef};
+
+use crate::{AutogradError, Operation, Value};
+
+const FONT_SIZE: f64 = 12.0;
+const CHAR_WIDTH: f64 = 7.2;
+const NODE_HEIGHT: f64 = 30.0;
+const PADDING: f64 = 12.0;
+const LAYER_GAP: f64 = 60.0;
+const NODE_GAP: f64 = 16.0;
+const OPERAND_SPREAD: f64 = 8.0;
+const LEGEND_SWATCH: f64 = 14.0;
+
+/// Payload of a node in the graph returned by [`Value::to_petgraph`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeData {
+    pub value: f64,
+    pub grad: f64,
+    pub operation: Option<Operation>,
+    pub label: String,
+}
+
+impl From<&Value<'_>> for NodeData {
+    fn from(value: &Value) -> Self {
+        Self {
+            value: value.value(),
+            grad: value.grad(),
+            operation: value.operation(),
+            label: value.get_label(),
+        }
+    }
+}
+
+/// Computation graph with an edge from every input to the value it feeds,
+/// weighted by the operand slot the input fills
+pub type ComputationGraph = Graph<NodeData, usize, Directed, u32>;
+
+/// What to colour value nodes by when rendering
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Heatmap {
+    #[default]
+    None,
+    /// `|grad|` on a log scale
+    Grad,
+    /// `|data|` on a log scale
+    Value,
+}
+
+const DEFAULT_FILL: &str = "#ffffff";
+const OP_FILL: &str = "#eeeeff";
+const ZERO_FILL: &str = "#9ecae1";
+const NON_FINITE_FILL: &str = "#ff00ff";
+// Light yellow through orange to dark red
+const HEAT_STOPS: [(f64, f64, f64); 3] = [(255.0, 255.0, 204.0), (253.0, 141.0, 60.0), (189.0, 0.0, 38.0)];
+
+#[derive(Debug, Clone)]
+struct RenderNode {
+    label: String,
+    is_op: bool,
+    fill: String,
+}
+
+type RenderGraph = Graph<RenderNode, RenderEdge, Directed, u32>;
+
+/// Input of an operation, labeled by the operand slot it fills
+#[derive(Debug, Clone)]
+struct RenderEdge {
+    slot: usize,
+    arity: usize,
+}
+
+impl Display for RenderEdge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.arity, self.slot) {
+            (1, _) => Ok(()),
+            (2, 0) => write!(f, "lhs"),
+            (2, _) => write!(f, "rhs"),
+            (_, slot) => write!(f, "{}", slot),
+        }
+    }
+}
+
+impl Display for RenderNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.label)
+    }
+}
+
+// Graph
+impl <'a>Value<'a> {
+    /// The graph ending at this value, for running petgraph algorithms on
+    pub fn to_petgraph(&self) -> ComputationGraph {
+        let mut graph = Graph::default();
+        // Add the output first so it always sits at index 0
+        let order = self.topological_order();
+        let indices: HashMap<usize, NodeIndex> = order.iter().rev()
+            .map(|value| (value.id(), graph.add_node(NodeData::from(value))))
+            .collect();
+        for value in order.iter().rev() {
+            for (slot, child) in value.children().iter().enumerate() {
+                graph.add_edge(indices[&child.id()], indices[&value.id()], slot);
+            }
+        }
+        graph
+    }
+
+    /// Like [`Value::to_petgraph`], but reports a malformed graph instead of panicking
+    pub fn try_graph(&self) -> Result<ComputationGraph, AutogradError> {
+        self.validate()?;
+        Ok(self.to_petgraph())
+    }
+
+    /// Render the graph in Graphviz DOT format
+    pub fn to_dot(&self) -> String {
+        self.to_dot_heatmap(Heatmap::None)
+    }
+
+    /// Render the graph in Graphviz DOT format with nodes coloured by `heatmap`, plus a legend
+    pub fn to_dot_heatmap(&self, heatmap: Heatmap) -> String {
+        let graph = self.to_petgraph();
+        let dot = Dot::with_attr_getters(
+            &render_graph(&graph, heatmap),
+            &[],
+            &|_, _| String::new(),
+            &|_, (_, node)| format!(
+                "shape={} style=filled fillcolor=\"{}\" fontcolor=\"{}\"",
+                if node.is_op { "circle" } else { "box" },
+                node.fill,
+                text_color(&node.fill),
+            ),
+        ).to_string();
+
+        let legend = legend(&graph, heatmap);
+        if legend.is_empty() {
+            return dot;
+        }
+        let mut cluster = "    subgraph cluster_legend {\n        label = \"legend\"\n".to_string();
+        for (i, (label, fill)) in legend.iter().enumerate() {
+            writeln!(cluster, "        legend{} [ label = \"{}\" shape=box style=filled fillcolor=\"{}\" fontcolor=\"{}\"]", i, label, fill, text_color(fill)).unwrap();
+        }
+        cluster.push_str("    }\n");
+        let end = dot.rfind('}').expect("DOT output ends in a brace");
+        format!("{}{}{}", &dot[..end], cluster, &dot[end..])
+    }
+
+    pub fn write_dot<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        std::fs::write(path, self.to_dot())
+    }
+
+    /// Render the graph as a standalone SVG image, laid out left to right ending at this value
+    pub fn to_svg(&self) -> String {
+        self.to_svg_heatmap(Heatmap::None)
+    }
+
+    /// Render the graph as an SVG image with nodes coloured by `heatmap`, plus a legend
+    pub fn to_svg_heatmap(&self, heatmap: Heatmap) -> String {
+        let graph = self.to_petgraph();
+        render_svg(&render_graph(&graph, heatmap), &legend(&graph, heatmap))
+    }
+
+    pub fn write_svg<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        std::fs::write(path, self.to_svg())
+    }
+
+    /// Render the graph as a Mermaid flowchart
+    pub fn to_mermaid(&self) -> String {
+        render_mermaid(&render_graph(&self.to_petgraph(), Heatmap::None))
+    }
+
+    pub fn write_mermaid<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        std::fs::write(path, self.to_mermaid())
+    }
+
+    /// Render the graph as a self-contained HTML page: the SVG drawing followed by a
+    /// table of every value, with rows and nodes highlighted together on hover
+    pub fn to_html(&self) -> String {
+        self.to_html_heatmap(Heatmap::None)
+    }
+
+    /// Render the graph as an HTML page with nodes coloured by `heatmap`, plus a legend
+    pub fn to_html_heatmap(&self, heatmap: Heatmap) -> String {
+        render_html(&self.to_petgraph(), heatmap)
+    }
+
+    pub fn write_html<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        std::fs::write(path, self.to_html())
+    }
+
+    /// Open the graph in an online Graphviz viewer
+    #[cfg(feature = "browser")]
+    pub fn graph(&self) {
+        self.graph_heatmap(Heatmap::None);
+    }
+
+    /// Open the graph in an online Graphviz viewer with nodes coloured by `heatmap`
+    #[cfg(feature = "browser")]
+    pub fn graph_heatmap(&self, heatmap: Heatmap) {
+        let url = format!("https://dreampuf.github.io/GraphvizOnline/#{}", urlencoding::encode(&self.to_dot_heatmap(heatmap)));
+        if let Err(e) = webbrowser::open(&url) {
+            println!("Error displaying graph: {:?}", e);
+        }
+    }
+}
+
+fn heat_input(node: &NodeData, heatmap: Heatmap) -> Option<f64> {
+    match heatmap {
+        Heatmap::None => None,
+        Heatmap::Grad => Some(node.grad),
+        Heatmap::Value => Some(node.value),
+    }
+}
+
+/// Range of `log10(|x|)` over the finite, non-zero inputs of the heatmap
+fn heat_range(graph: &ComputationGraph, heatmap: Heatmap) -> Option<(f64, f64)> {
+    graph.node_weights()
+        .filter_map(|node| heat_input(node, heatmap))
+        .filter(|x| x.is_finite() && *x != 0.0)
+        .map(|x| x.abs().log10())
+        .fold(None, |range, x| match range {
+            None => Some((x, x)),
+            Some((min, max)) => Some((f64::min(min, x), f64::max(max, x))),
+        })
+}
+
+fn heat_color(t: f64) -> String {
+    let t = t.clamp(0.0, 1.0) * (HEAT_STOPS.len() - 1) as f64;
+    let i = (t as usize).min(HEAT_STOPS.len() - 2);
+    let (from, to, t) = (HEAT_STOPS[i], HEAT_STOPS[i + 1], t - i as f64);
+    let lerp = |a: f64, b: f64| (a + (b - a) * t).round() as u8;
+    format!("#{:02x}{:02x}{:02x}", lerp(from.0, to.0), lerp(from.1, to.1), lerp(from.2, to.2))
+}
+
+fn node_fill(x: f64, range: Option<(f64, f64)>) -> String {
+    if !x.is_finite() {
+        return NON_FINITE_FILL.to_string();
+    }
+    match range {
+        _ if x == 0.0 => ZERO_FILL.to_string(),
+        Some((min, max)) if max > min => heat_color((x.abs().log10() - min) / (max - min)),
+        _ => heat_color(1.0),
+    }
+}
+
+/// Black or white, whichever reads better on a `#rrggbb` fill
+fn text_color(fill: &str) -> &'static str {
+    let channel = |i: usize| u8::from_str_radix(&fill[i..i + 2], 16).unwrap_or(255) as f64;
+    let luminance = 0.299 * channel(1) + 0.587 * channel(3) + 0.114 * channel(5);
+    if luminance < 128.0 { "#ffffff" } else { "#000000" }
+}
+
+fn legend(graph: &ComputationGraph, heatmap: Heatmap) -> Vec<(String, String)> {
+    let name = match heatmap {
+        Heatmap::None => return vec![],
+        Heatmap::Grad => "|grad|",
+        Heatmap::Value => "|data|",
+    };
+    let mut legend = vec![];
+    if let Some((min, max)) = heat_range(graph, heatmap) {
+        legend.push((format!("{} = {:.1e}", name, 10f64.powf(min)), heat_color(if max > min { 0.0 } else { 1.0 })));
+        if max > min {
+            legend.push((format!("{} = {:.1e}", name, 10f64.powf((min + max) / 2.0)), heat_color(0.5)));
+            legend.push((format!("{} = {:.1e}", name, 10f64.powf(max)), heat_color(1.0)));
+        }
+    }
+    legend.push((format!("{} = 0", name), ZERO_FILL.to_string()));
+    legend.push(("NaN / inf".to_string(), NON_FINITE_FILL.to_string()));
+    legend
+}
+
+/// Split every operation out into its own node for drawing
+fn render_graph(graph: &ComputationGraph, heatmap: Heatmap) -> RenderGraph {
+    let range = heat_range(graph, heatmap);
+    let mut render = Graph::default();
+    for node in graph.node_weights() {
+        render.add_node(RenderNode {
+            label: format!("{} | data: {} | grad: {}", node.label, node.value, node.grad),
+            is_op: false,
+            fill: heat_input(node, heatmap).map_or(DEFAULT_FILL.to_string(), |x| node_fill(x, range)),
+        });
+    }
+
+    let mut op_nodes = vec![None; graph.node_count()];
+    for node in graph.node_indices() {
+        if let Some(operation) = graph[node].operation {
+            let op_node = render.add_node(RenderNode { label: operation.to_string(), is_op: true, fill: OP_FILL.to_string() });
+            render.add_edge(op_node, node, RenderEdge { slot: 0, arity: 1 });
+            op_nodes[node.index()] = Some(op_node);
+        }
+    }
+    for edge in graph.edge_references() {
+        let op_node = op_nodes[edge.target().index()].expect("only operations have inputs");
+        let arity = graph.edges_directed(edge.target(), Direction::Incoming).count();
+        render.add_edge(edge.source(), op_node, RenderEdge { slot: *edge.weight(), arity });
+    }
+    render
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Lay the graph out in columns by distance from the output, then draw it
+fn render_svg(graph: &RenderGraph, legend: &[(String, String)]) -> String {
+    // Edges point towards the output, so walk them backwards to find each node's column
+    let order = toposort(graph, None).expect("computation graphs are acyclic");
+    let mut layers = vec![0; graph.node_count()];
+    for &node in order.iter().rev() {
+        layers[node.index()] = graph.neighbors_directed(node, Direction::Outgoing)
+            .map(|parent| layers[parent.index()] + 1)
+            .max()
+            .unwrap_or(0);
+    }
+    let depth = layers.iter().copied().max().unwrap_or(0);
+
+    let width_of = |node: NodeIndex| (graph[node].label.chars().count() as f64 * CHAR_WIDTH + 2.0 * PADDING).max(NODE_HEIGHT);
+    let mut columns: Vec<Vec<NodeIndex>> = vec![vec![]; depth + 1];
+    for node in graph.node_indices() {
+        columns[depth - layers[node.index()]].push(node);
+    }
+    let column_widths: Vec<f64> = columns.iter()
+        .map(|column| column.iter().map(|n| width_of(*n)).fold(0.0, f64::max))
+        .collect();
+    let tallest = columns.iter().map(|c| c.len()).max().unwrap_or(0) as f64;
+    let height = tallest * (NODE_HEIGHT + NODE_GAP) + NODE_GAP;
+
+    // Centre of every node
+    let mut positions = vec![(0.0, 0.0); graph.node_count()];
+    let mut x = NODE_GAP;
+    for (column, width) in columns.iter().zip(&column_widths) {
+        let top = (height - column.len() as f64 * (NODE_HEIGHT + NODE_GAP) + NODE_GAP) / 2.0;
+        for (i, node) in column.iter().enumerate() {
+            positions[node.index()] = (x + width / 2.0, top + i as f64 * (NODE_HEIGHT + NODE_GAP) + NODE_HEIGHT / 2.0);
+        }
+        x += width + LAYER_GAP;
+    }
+    let legend_width: f64 = legend.iter()
+        .map(|(label, _)| LEGEND_SWATCH + 6.0 + label.chars().count() as f64 * CHAR_WIDTH + NODE_GAP)
+        .sum();
+    let width = (x - LAYER_GAP + NODE_GAP).max(legend_width + NODE_GAP);
+    let legend_top = height;
+    let height = if legend.is_empty() { height } else { height + LEGEND_SWATCH + NODE_GAP };
+
+    let mut svg = String::new();
+    writeln!(svg, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#, w = width, h = height).unwrap();
+    writeln!(svg, r##"<defs><marker id="arrow" viewBox="0 0 10 10" refX="10" refY="5" markerWidth="6" markerHeight="6" orient="auto"><path d="M 0 0 L 10 5 L 0 10 z" fill="#555"/></marker></defs>"##).unwrap();
+    for edge in graph.edge_indices() {
+        let (from, to) = graph.edge_endpoints(edge).unwrap();
+        let (x1, y1) = positions[from.index()];
+        let (x2, y2) = positions[to.index()];
+        let (x1, x2) = (x1 + width_of(from) / 2.0, x2 - width_of(to) / 2.0);
+        // Spread the inputs of an operation so repeated operands stay distinguishable
+        let operand = &graph[edge];
+        let y2 = y2 + (operand.slot as f64 - (operand.arity - 1) as f64 / 2.0) * OPERAND_SPREAD;
+        let mid = (x1 + x2) / 2.0;
+        writeln!(svg, r##"<path d="M {x1} {y1} C {mid} {y1}, {mid} {y2}, {x2} {y2}" fill="none" stroke="#555" marker-end="url(#arrow)"/>"##).unwrap();
+        let label = operand.to_string();
+        if !label.is_empty() {
+            writeln!(svg, r##"<text x="{mid}" y="{}" text-anchor="middle" font-family="monospace" font-size="{}" fill="#555">{label}</text>"##, (y1 + y2) / 2.0 - 4.0, FONT_SIZE - 2.0).unwrap();
+        }
+    }
+    for node in graph.node_indices() {
+        let (cx, cy) = positions[node.index()];
+        let w = width_of(node);
+        writeln!(svg, r#"<g class="node" data-node="{}"><title>{}</title>"#, node.index(), escape_xml(&graph[node].label)).unwrap();
+        if graph[node].is_op {
+            writeln!(svg, r##"<ellipse cx="{cx}" cy="{cy}" rx="{}" ry="{}" fill="{}" stroke="#333"/>"##, w / 2.0, NODE_HEIGHT / 2.0, graph[node].fill).unwrap();
+        } else {
+            writeln!(svg, r##"<rect x="{}" y="{}" width="{w}" height="{NODE_HEIGHT}" rx="4" fill="{}" stroke="#333"/>"##, cx - w / 2.0, cy - NODE_HEIGHT / 2.0, graph[node].fill).unwrap();
+        }
+        writeln!(svg, r#"<text x="{cx}" y="{cy}" text-anchor="middle" dominant-baseline="central" font-family="monospace" font-size="{FONT_SIZE}" fill="{}">{}</text>"#, text_color(&graph[node].fill), escape_xml(&graph[node].label)).unwrap();
+        svg.push_str("</g>\n");
+    }
+    let mut x = NODE_GAP;
+    for (label, fill) in legend {
+        writeln!(svg, r##"<rect x="{x}" y="{legend_top}" width="{LEGEND_SWATCH}" height="{LEGEND_SWATCH}" fill="{fill}" stroke="#333"/>"##).unwrap();
+        writeln!(svg, r#"<text x="{}" y="{}" dominant-baseline="central" font-family="monospace" font-size="{FONT_SIZE}">{}</text>"#, x + LEGEND_SWATCH + 6.0, legend_top + LEGEND_SWATCH / 2.0, escape_xml(label)).unwrap();
+        x += LEGEND_SWATCH + 6.0 + label.chars().count() as f64 * CHAR_WIDTH + NODE_GAP;
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn render_mermaid(graph: &RenderGraph) -> String {
+    let mut mermaid = "flowchart LR\n".to_string();
+    for node in graph.node_indices() {
+        let label = graph[node].label.replace('"', "#quot;");
+        if graph[node].is_op {
+            writeln!(mermaid, "    n{}((\"{}\"))", node.index(), label).unwrap();
+        } else {
+            writeln!(mermaid, "    n{}[\"{}\"]", node.index(), label).unwrap();
+        }
+    }
+    for edge in graph.edge_references() {
+        let label = edge.weight().to_string();
+        if label.is_empty() {
+            writeln!(mermaid, "    n{} --> n{}", edge.source().index(), edge.target().index()).unwrap();
+        } else {
+            writeln!(mermaid, "    n{} -->|{}| n{}", edge.source().index(), label, edge.target().index()).unwrap();
+        }
+    }
+    mermaid
+}
+
+const HTML_STYLE: &str = "\
+body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; margin-top: 2em; font-family: monospace; }
+th, td { border: 1px solid #ccc; padding: 4px 10px; text-align: right; }
+th:first-child, td:first-child { text-align: left; }
+.highlight rect, .highlight ellipse { fill: #ffd; stroke-width: 2; }
+tr.highlight { background: #ffd; }";
+
+// Highlight every element sharing the hovered element's data-node
+const HTML_SCRIPT: &str = "\
+document.querySelectorAll('[data-node]').forEach(el => {
+  const matching = () => document.querySelectorAll(`[data-node=\"${el.dataset.node}\"]`);
+  el.addEventListener('mouseenter', () => matching().forEach(m => m.classList.add('highlight')));
+  el.addEventListener('mouseleave', () => matching().forEach(m => m.classList.remove('highlight')));
+});";
+
+fn render_html(graph: &ComputationGraph, heatmap: Heatmap) -> String {
+    let mut html = String::new();
+    writeln!(html, "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Computation graph</title>").unwrap();
+    writeln!(html, "<style>\n{}\n</style>\n</head>\n<body>", HTML_STYLE).unwrap();
+    html.push_str(&render_svg(&render_graph(graph, heatmap), &legend(graph, heatmap)));
+    writeln!(html, "<table>\n<tr><th>label</th><th>op</th><th>data</th><th>grad</th></tr>").unwrap();
+    // Value nodes keep their index in the rendered graph, so rows line up with the drawing
+    for node in graph.node_indices() {
+        let data = &graph[node];
+        let operation = data.operation.map(|op| op.to_string()).unwrap_or_default();
+        writeln!(
+            html,
+            r#"<tr data-node="{}"><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>"#,
+            node.index(), escape_xml(&data.label), escape_xml(&operation), data.value, data.grad,
+        ).unwrap();
+    }
+    writeln!(html, "</table>\n<script>\n{}\n</script>\n</body>\n</html>", HTML_SCRIPT).unwrap();
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use petgraph::graph::NodeIndex;
+
+    use super::*;
+    use crate::{Arena, Operation};
+
+    #[test]
+    fn dot_contains_nodes() {
+        let arena = Arena::new();
+        let a = arena.value(2.0).label("a");
+        let out = (a * 3.0).tanh().label("out");
+        let dot = out.to_dot();
+
+        assert!(dot.starts_with("digraph"));
+        assert!(dot.contains("a | data: 2 | grad: 0"));
+        assert!(dot.contains("label = \"tanh\""));
+        assert!(dot.contains("label = \"*\""));
+    }
+
+    #[test]
+    fn svg_escapes_labels() {
+        let arena = Arena::new();
+        let out = (arena.value(1.0).label("<x>") + 1.0).label("out");
+        let svg = out.to_svg();
+
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert!(svg.contains("&lt;x&gt; | data: 1"));
+        assert_eq!(svg.matches("marker-end").count(), 3);
+        assert_eq!(svg.matches("<ellipse").count(), 1);
+    }
+
+    #[test]
+    fn typed_petgraph() {
+        let arena = Arena::new();
+        let a = arena.value(2.0).label("a");
+        let b = arena.value(-1.0).label("b");
+        let out = (a - b).exp().label("out");
+        out.backward();
+        let graph = out.to_petgraph();
+
+        assert_eq!(graph.node_count(), 4);
+        assert_eq!(graph.edge_count(), 3);
+        let root = &graph[NodeIndex::new(0)];
+        assert_eq!(root.label, "out");
+        assert_eq!(root.operation, Some(Operation::Exp));
+        assert_eq!(root.grad, 1.0);
+
+        let b_node = graph.node_indices().find(|n| graph[*n].label == "b").unwrap();
+        let edge = graph.edges(b_node).next().unwrap();
+        assert_eq!(*edge.weight(), 1);
+        assert_eq!(graph[b_node].grad, -(3.0f64).exp());
+
+        // Every edge points towards the output
+        let order = petgraph::algo::toposort(&graph, None).unwrap();
+        assert_eq!(order.last(), Some(&NodeIndex::new(0)));
+    }
+
+    #[test]
+    fn shared_nodes_drawn_once() {
+        let arena = Arena::new();
+        let a = arena.value(2.0).label("a");
+        let b = arena.value(3.0).label("b");
+        let c = arena.value(4.0).label("c");
+        let out = ((a * b) + (a * c)).label("out");
+        let graph = out.to_petgraph();
+
+        assert_eq!(graph.node_count(), 6);
+        assert_eq!(graph.edge_count(), 6);
+        let a_node = graph.node_indices().find(|n| graph[*n].label == "a").unwrap();
+        assert_eq!(graph.edges(a_node).count(), 2);
+        assert_eq!(out.to_dot().matches("a | data: 2").count(), 1);
+    }
+
+    #[test]
+    fn operand_slots_labeled() {
+        let arena = Arena::new();
+        let a = arena.value(2.0).label("a");
+        let out = (a / a).tanh();
+        let dot = out.to_dot();
+
+        assert_eq!(out.to_petgraph().edge_count(), 3);
+        assert!(dot.contains("label = \"lhs\""));
+        assert!(dot.contains("label = \"rhs\""));
+        let svg = out.to_svg();
+        assert!(svg.contains(">lhs</text>") && svg.contains(">rhs</text>"));
+    }
+
+    #[test]
+    fn mermaid_flowchart() {
+        let arena = Arena::new();
+        let a = arena.value(2.0).label("a");
+        let out = (a * a).label("out");
+        let mermaid = out.to_mermaid();
+
+        let expected = "\
+flowchart LR
+    n0[\"out | data: 4 | grad: 0\"]
+    n1[\"a | data: 2 | grad: 0\"]
+    n2((\"*\"))
+    n2 --> n0
+    n1 -->|lhs| n2
+    n1 -->|rhs| n2
+";
+        assert_eq!(mermaid, expected);
+    }
+
+    #[test]
+    fn html_page() {
+        let arena = Arena::new();
+        let a = arena.value(2.0).label("a");
+        let out = (a + 1.0).label("out");
+        out.backward();
+        let html = out.to_html();
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<svg"));
+        assert!(html.contains(r#"<tr data-node="0"><td>out</td><td>+</td><td>3</td><td>1</td></tr>"#));
+        assert_eq!(html.matches(r#"<g class="node" data-node="1">"#).count(), 1);
+        assert!(html.trim_end().ends_with("</html>"));
+    }
+
+    #[test]
+    fn heatmap_colors() {
+        let arena = Arena::new();
+        let a = arena.value(1.0).label("a");
+        let b = arena.value(0.0).label("b");
+        let c = arena.value(f64::NAN).label("c");
+        let out = (a * 1000.0 + b * a + c * 0.0).label("out");
+        out.backward();
+        let graph = out.to_petgraph();
+        let render = render_graph(&graph, Heatmap::Grad);
+        let fill = |label: &str| {
+            let node = graph.node_indices().find(|n| graph[*n].label == label).unwrap();
+            render[node].fill.clone()
+        };
+
+        // |grad| of a is 1000, the largest, and out is 1, the smallest
+        assert_eq!(fill("a"), heat_color(1.0));
+        assert_eq!(fill("out"), heat_color(0.0));
+        assert_eq!(fill("c"), ZERO_FILL);
+        assert_eq!(heat_range(&graph, Heatmap::Grad), Some((0.0, 3.0)));
+        assert_eq!(render_graph(&graph, Heatmap::Value)[NodeIndex::new(0)].fill, NON_FINITE_FILL);
+        assert_eq!(render_graph(&graph, Heatmap::None)[NodeIndex::new(0)].fill, DEFAULT_FILL);
+    }
+
+    #[test]
+    fn heatmap_legend() {
+        let arena = Arena::new();
+        let out = (arena.value(2.0) * 3.0).label("out");
+        out.backward();
+
+        let dot = out.to_dot_heatmap(Heatmap::Grad);
+        assert!(dot.contains("subgraph cluster_legend"));
+        assert!(dot.contains("|grad| = 1.0e0"));
+        assert!(dot.contains("|grad| = 3.0e0"));
+        assert!(dot.trim_end().ends_with('}'));
+        assert!(!out.to_dot().contains("cluster_legend"));
+
+        let svg = out.to_svg_heatmap(Heatmap::Value);
+        assert!(svg.contains("NaN / inf"));
+        assert!(svg.contains("|data| = 6.0e0"));
+    }
+
+    #[test]
+    fn writes_files() {
+        let arena = Arena::new();
+        let out = arena.value(1.0).exp().label("out");
+        let path = std::env::temp_dir().join(format!("micrograd-{}.dot", std::process::id()));
+        out.write_dot(&path).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), out.to_dot());
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+
+use crate::{Arena, Value};
+
+/// Dense Jacobian of `f` at `x`, with one row per output and one column per input.
+/// Uses a forward sweep per input when there are more outputs than inputs, otherwise
+/// a reverse sweep per output.
+pub fn jacobian<F>(f: F, x: &[f64]) -> Vec<Vec<f64>>
+where F: for<'a> Fn(&[Value<'a>]) -> Vec<Value<'a>> {
+    let arena = Arena::new();
+    let inputs: Vec<Value> = x.iter().map(|x| arena.value(*x)).collect();
+    let outputs = f(&inputs);
+    dense_jacobian(&outputs, &inputs)
+}
+
+/// Dense Hessian of the scalar function `f` at `x`, computed forward-over-reverse
+pub fn hessian<F>(f: F, x: &[f64]) -> Vec<Vec<f64>>
+where F: for<'a> Fn(&[Value<'a>]) -> Value<'a> {
+    let arena = Arena::new();
+    let inputs: Vec<Value> = x.iter().map(|x| arena.value(*x)).collect();
+    let gradient = f(&inputs).grad_graph(&inputs);
+    forward_jacobian(&gradient, &inputs)
+}
+
+fn dense_jacobian(outputs: &[Value], inputs: &[Value]) -> Vec<Vec<f64>> {
+    if outputs.len() > inputs.len() {
+        forward_jacobian(outputs, inputs)
+    } else {
+        reverse_jacobian(outputs, inputs)
+    }
+}
+
+fn reverse_jacobian(outputs: &[Value], inputs: &[Value]) -> Vec<Vec<f64>> {
+    outputs.iter().map(|output| {
+        output.arena().zero_grad();
+        output.backward();
+        inputs.iter().map(|x| x.grad()).collect()
+    }).collect()
+}
+
+fn forward_jacobian(outputs: &[Value], inputs: &[Value]) -> Vec<Vec<f64>> {
+    let mut columns = vec![];
+    for i in 0..inputs.len() {
+        let direction: Vec<f64> = (0..inputs.len()).map(|j| if i == j { 1.0 } else { 0.0 }).collect();
+        columns.push(forward_sweep(outputs, inputs, &direction));
+    }
+    (0..outputs.len()).map(|row| columns.iter().map(|column| column[row]).collect()).collect()
+}
+
+/// Directional derivative of every output along `direction`, propagated through the recorded graph
+fn forward_sweep(outputs: &[Value], inputs: &[Value], direction: &[f64]) -> Vec<f64> {
+    // Children are always created before their parents, so arena order is a topological order
+    let mut nodes: Vec<Value> = outputs.iter().flat_map(|o| o.topological_order()).collect();
+    nodes.sort_by_key(|node| node.id());
+    nodes.dedup();
+
+    let mut tangents: HashMap<usize, f64> = inputs.iter().zip(direction).map(|(x, d)| (x.id(), *d)).collect();
+    for node in nodes {
+        let Some(operation) = node.operation() else { continue };
+        let children = node.children();
+        let values: Vec<f64> = children.iter().map(|c| c.value()).collect();
+        let derivatives = node.child_grads(operation, &values, 1.0).unwrap_or_else(|error| panic!("{}", error));
+        let tangent = derivatives.iter().zip(&children).enumerate()
+            .map(|(slot, (derivative, child))| match tangents.get(&child.id()) {
+                // Skip inputs without a tangent so infinite derivatives don't turn into NaN
+                Some(t) if *t != 0.0 && operation.differentiable(slot) => derivative * t,
+                _ => 0.0,
+            })
+            .sum();
+        tangents.insert(node.id(), tangent);
+    }
+    outputs.iter().map(|o| tangents.get(&o.id()).copied().unwrap_or(0.0)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_matrix_close(a: &[Vec<f64>], b: &[Vec<f64>]) {
+        assert_eq!(a.len(), b.len());
+        for (row_a, row_b) in a.iter().zip(b) {
+            for (x, y) in row_a.iter().zip(row_b) {
+                assert!((x - y).abs() < 1e-9, "{:?} != {:?}", a, b);
+            }
+        }
+    }
+
+    // f(x, y) = (xy, x + y^2, tanh(x))
+    fn wide<'a>(x: &[Value<'a>]) -> Vec<Value<'a>> {
+        vec![x[0] * x[1], x[0] + x[1].pow(2.0), x[0].tanh()]
+    }
+
+    #[test]
+    fn jacobian_forward() {
+        let t = 0.5f64.tanh();
+        let expected = vec![vec![2.0, 0.5], vec![1.0, 4.0], vec![1.0 - t * t, 0.0]];
+        assert_matrix_close(&jacobian(wide, &[0.5, 2.0]), &expected);
+    }
+
+    #[test]
+    fn jacobian_reverse() {
+        // f(x, y, z) = (xyz, x - z)
+        let j = jacobian(|x| vec![x[0] * x[1] * x[2], x[0] - x[2]], &[1.0, 2.0, 3.0]);
+        assert_matrix_close(&j, &[vec![6.0, 3.0, 2.0], vec![1.0, 0.0, -1.0]]);
+    }
+
+    #[test]
+    fn sweeps_agree() {
+        let arena = Arena::new();
+        let inputs = [arena.value(0.3), arena.value(-0.8)];
+        let outputs = [(inputs[0] / inputs[1]).exp(), inputs[0].sigmoid() * inputs[1].softplus()];
+        assert_matrix_close(&forward_jacobian(&outputs, &inputs), &reverse_jacobian(&outputs, &inputs));
+    }
+
+    #[test]
+    fn hessian_of_polynomial() {
+        // f = x^2 y + y^3, H = [[2y, 2x], [2x, 6y]]
+        let h = hessian(|x| x[0] * x[0] * x[1] + x[1].pow(3.0), &[1.5, -0.5]);
+        assert_matrix_close(&h, &[vec![-1.0, 3.0], vec![3.0, -3.0]]);
+    }
+}
@@ -0,0 +1,24 @@
+mod error;
+pub use error::AutogradError;
+mod value;
+pub use value::*;
+mod graph;
+pub use graph::{NodeData, ComputationGraph, Heatmap};
+pub use petgraph;
+mod tree;
+mod scalar;
+pub use scalar::Scalar;
+mod dual;
+pub use dual::{Dual, jvp};
+mod jacobian;
+pub use jacobian::{jacobian, hessian};
+mod gradcheck;
+pub use gradcheck::{gradcheck, GradcheckOptions, GradcheckReport, LeafCheck};
+mod custom;
+pub use custom::{CustomOp, CustomOpId, register_op};
+pub mod nn;
+pub mod optim;
+pub mod scheduler;
+pub mod loss;
+#[cfg(test)]
+mod test_util;
@@ -0,0 +1,183 @@
+use crate::Value;
+
+fn sum<'a>(values: impl IntoIterator<Item = Value<'a>>) -> Value<'a> {
+    values.into_iter().reduce(|a, b| a + b).expect("loss of an empty slice")
+}
+
+fn mean<'a>(values: Vec<Value<'a>>) -> Value<'a> {
+    let n = values.len() as f64;
+    sum(values) / n
+}
+
+// Floor on the logs in `binary_cross_entropy`, matching PyTorch
+const LN_FLOOR: f64 = -100.0;
+
+// ln(x), clamped at LN_FLOOR so a probability of exactly 0 gives a finite loss and no gradient
+fn clamped_ln(x: Value) -> Value {
+    if x.value().ln() > LN_FLOOR {
+        x.ln()
+    } else {
+        x.arena().constant(LN_FLOOR)
+    }
+}
+
+fn check_lengths<T>(predictions: &[Value], targets: &[T]) {
+    assert_eq!(predictions.len(), targets.len(), "predictions and targets differ in length");
+}
+
+/// Mean squared error
+pub fn mse<'a>(predictions: &[Value<'a>], targets: &[f64]) -> Value<'a> {
+    check_lengths(predictions, targets);
+    mean(predictions.iter().zip(targets).map(|(p, t)| (*p - *t).pow(2.0)).collect())
+}
+
+/// Mean absolute error
+pub fn mae<'a>(predictions: &[Value<'a>], targets: &[f64]) -> Value<'a> {
+    check_lengths(predictions, targets);
+    mean(predictions.iter().zip(targets).map(|(p, t)| (*p - *t).abs()).collect())
+}
+
+/// Quadratic for errors below `delta`, linear above
+pub fn huber<'a>(predictions: &[Value<'a>], targets: &[f64], delta: f64) -> Value<'a> {
+    check_lengths(predictions, targets);
+    mean(predictions.iter().zip(targets).map(|(p, t)| {
+        let error = (*p - *t).abs();
+        if error.value() <= delta {
+            0.5 * error.pow(2.0)
+        } else {
+            delta * (error - 0.5 * delta)
+        }
+    }).collect())
+}
+
+/// SVM max-margin loss, with targets of -1 or 1
+pub fn hinge<'a>(scores: &[Value<'a>], targets: &[f64]) -> Value<'a> {
+    check_lengths(scores, targets);
+    mean(scores.iter().zip(targets).map(|(s, t)| (1.0 - *s * *t).relu()).collect())
+}
+
+/// Multiclass SVM loss for one sample: the sum of margin violations against the target class
+pub fn multi_margin<'a>(scores: &[Value<'a>], target: usize) -> Value<'a> {
+    let target_score = scores[target];
+    let violations: Vec<Value> = scores.iter().enumerate()
+        .filter(|(i, _)| *i != target)
+        .map(|(_, s)| (*s - target_score + 1.0).relu())
+        .collect();
+    if violations.is_empty() {
+        return target_score.arena().constant(0.0);
+    }
+    sum(violations)
+}
+
+/// Binary cross-entropy on probabilities in [0, 1], with the logs clamped at -100 so saturated
+/// probabilities stay finite. Prefer [`binary_cross_entropy_with_logits`] when the probabilities
+/// come from a sigmoid.
+pub fn binary_cross_entropy<'a>(probabilities: &[Value<'a>], targets: &[f64]) -> Value<'a> {
+    check_lengths(probabilities, targets);
+    mean(probabilities.iter().zip(targets)
+        .map(|(p, t)| -(*t * clamped_ln(*p) + (1.0 - *t) * clamped_ln(1.0 - *p)))
+        .collect())
+}
+
+/// Binary cross-entropy on raw logits, computed stably as `softplus(x) - x * y`
+pub fn binary_cross_entropy_with_logits<'a>(logits: &[Value<'a>], targets: &[f64]) -> Value<'a> {
+    check_lengths(logits, targets);
+    mean(logits.iter().zip(targets).map(|(x, t)| x.softplus() - *x * *t).collect())
+}
+
+/// `ln(sum(exp(x)))`, shifted by the maximum so no term overflows
+pub fn log_sum_exp<'a>(values: &[Value<'a>]) -> Value<'a> {
+    // The shift cancels out exactly, so it can be a constant without changing the gradient
+    let max = values.iter().map(|v| v.value()).fold(f64::NEG_INFINITY, f64::max);
+    sum(values.iter().map(|v| (*v - max).exp())).ln() + max
+}
+
+/// Softmax cross-entropy for one sample, from raw logits and the target class
+pub fn cross_entropy<'a>(logits: &[Value<'a>], target: usize) -> Value<'a> {
+    log_sum_exp(logits) - logits[target]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_util::assert_close, Arena};
+
+    #[test]
+    fn regression_losses() {
+        let arena = Arena::new();
+        let p = [arena.value(1.0), arena.value(4.0)];
+        let targets = [2.0, 1.0];
+
+        let loss = mse(&p, &targets);
+        loss.backward();
+        assert_close(loss.value(), 5.0);
+        assert_close(p[0].grad(), -1.0);
+        assert_close(p[1].grad(), 3.0);
+
+        assert_close(mae(&p, &targets).value(), 2.0);
+        // 0.5 * 1^2 and 1.5 * (3 - 0.75)
+        assert_close(huber(&p, &targets, 1.5).value(), (0.5 + 1.5 * 2.25) / 2.0);
+    }
+
+    #[test]
+    fn hinge_losses() {
+        let arena = Arena::new();
+        let scores = [arena.value(0.5), arena.value(-2.0)];
+        assert_close(hinge(&scores, &[1.0, -1.0]).value(), 0.25);
+
+        let loss = multi_margin(&scores, 1);
+        loss.backward();
+        assert_close(loss.value(), 3.5);
+        assert_close(scores[0].grad(), 1.0);
+        assert_close(scores[1].grad(), -1.0);
+    }
+
+    #[test]
+    fn binary_cross_entropy_matches_logits() {
+        let arena = Arena::new();
+        let logits = [arena.value(0.3), arena.value(-1.2)];
+        let targets = [1.0, 0.0];
+        let probabilities: Vec<Value> = logits.iter().map(|x| x.sigmoid()).collect();
+
+        let from_probabilities = binary_cross_entropy(&probabilities, &targets);
+        let from_logits = binary_cross_entropy_with_logits(&logits, &targets);
+        assert_close(from_probabilities.value(), from_logits.value());
+
+        from_logits.backward();
+        // d/dx = (sigmoid(x) - y) / n
+        assert_close(logits[0].grad(), (probabilities[0].value() - 1.0) / 2.0);
+    }
+
+    #[test]
+    fn binary_cross_entropy_saturated() {
+        let arena = Arena::new();
+        let logit = arena.value(40.0);
+        let p = logit.sigmoid();
+        assert_eq!(p.value(), 1.0);
+
+        let matching = binary_cross_entropy(&[p], &[1.0]);
+        matching.backward();
+        assert_close(matching.value(), 0.0);
+        assert!(logit.grad().is_finite());
+
+        // Both terms hit the floor of -100
+        let wrong = binary_cross_entropy(&[p, arena.constant(0.0)], &[0.0, 1.0]);
+        wrong.backward();
+        assert_close(wrong.value(), 100.0);
+        assert!(logit.grad().is_finite());
+    }
+
+    #[test]
+    fn cross_entropy_is_stable() {
+        let arena = Arena::new();
+        let logits = [arena.value(1000.0), arena.value(999.0), arena.value(-1000.0)];
+        let loss = cross_entropy(&logits, 1);
+        loss.backward();
+
+        let softmax_0 = 1.0 / (1.0 + (-1.0f64).exp());
+        assert_close(loss.value(), 1.0 + (1.0 + (-1.0f64).exp()).ln());
+        assert_close(logits[0].grad(), softmax_0);
+        assert_close(logits[1].grad(), 1.0 - softmax_0 - 1.0);
+        assert_close(logits[2].grad(), 0.0);
+    }
+}
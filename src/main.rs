@@ -1,29 +1,33 @@
-mod value;
-use value::*;
+use micrograd::*;
 
 fn main() {
-    let mut a = Value::new(2.0).label("a");
+    let arena = Arena::new();
+
+    let a = arena.value(2.0).label("a");
     println!("{}", a);
-    let mut b = Value::new(-3.0).label("b");
+    let b = arena.value(-3.0).label("b");
 
-    let mut c = Value::new(10.0).label("c");
+    let c = arena.value(10.0).label("c");
 
-    let mut e = (&mut a * &mut b).label("e");
+    let e = (a * b).label("e");
 
-    let mut d = (&mut c + &mut e).label("d");
+    let d = (c + e).label("d");
 
-    let mut f = Value::new(-2.0).label("f");
+    let f = arena.value(-2.0).label("f");
 
-    let mut l = (&mut d * &mut f).label("L");
+    let l = (d * f).label("L");
 
-    let mut out = l.tanh().label("out");
+    let out = l.tanh().label("out");
 
-    out.grad = 1.0;
     out.backward();
 
-    out.graph();
+    println!("{}", out.to_dot());
 
-    out.apply_grad(0.1);
+    for leaf in [a, b, c, f] {
+        leaf.apply_grad(0.1);
+    }
 
-    out.graph();
+    if let Err(e) = out.write_svg("graph.svg") {
+        println!("Error writing graph: {:?}", e);
+    }
 }
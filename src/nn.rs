@@ -0,0 +1,218 @@
+use rand::Rng;
+
+use crate::{Arena, Value};
+
+/// Non-linearity applied to the output of a neuron
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Activation {
+    Linear,
+    Tanh,
+    Relu,
+    LeakyRelu(f64),
+    Sigmoid,
+    Gelu,
+    Softplus,
+    Elu(f64),
+    Silu,
+}
+
+impl Activation {
+    pub fn apply<'a>(self, x: Value<'a>) -> Value<'a> {
+        match self {
+            Activation::Linear => x,
+            Activation::Tanh => x.tanh(),
+            Activation::Relu => x.relu(),
+            Activation::LeakyRelu(slope) => x.leaky_relu(slope),
+            Activation::Sigmoid => x.sigmoid(),
+            Activation::Gelu => x.gelu(),
+            Activation::Softplus => x.softplus(),
+            Activation::Elu(alpha) => x.elu(alpha),
+            Activation::Silu => x.silu(),
+        }
+    }
+}
+
+/// Anything holding trainable parameters
+pub trait Module<'a> {
+    /// Every trainable leaf along with its path, e.g. `layer1.neuron3.w2`
+    fn named_parameters(&self) -> Vec<(String, Value<'a>)>;
+
+    fn parameters(&self) -> Vec<Value<'a>> {
+        self.named_parameters().into_iter().map(|(_, p)| p).collect()
+    }
+
+    /// Reset the gradients of all parameters, to be called between training steps
+    fn zero_grad(&self) {
+        for p in self.parameters() {
+            p.set_grad(0.0);
+        }
+    }
+}
+
+/// Prefix every name with `prefix`
+fn nest<'a>(prefix: String, parameters: Vec<(String, Value<'a>)>) -> Vec<(String, Value<'a>)> {
+    parameters.into_iter().map(|(name, p)| (format!("{}.{}", prefix, name), p)).collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct Neuron<'a> {
+    pub weights: Vec<Value<'a>>,
+    pub bias: Value<'a>,
+    pub activation: Activation,
+}
+
+impl <'a>Neuron<'a> {
+    pub fn new(arena: &'a Arena, inputs: usize, activation: Activation) -> Self {
+        let mut rng = rand::thread_rng();
+        Self {
+            weights: (0..inputs).map(|_| arena.value(rng.gen_range(-1.0..1.0))).collect(),
+            bias: arena.value(0.0),
+            activation,
+        }
+    }
+
+    pub fn forward(&self, inputs: &[Value<'a>]) -> Value<'a> {
+        assert_eq!(inputs.len(), self.weights.len(), "expected {} inputs", self.weights.len());
+        let sum = self.weights.iter().zip(inputs)
+            .fold(self.bias, |acc, (w, x)| acc + *w * *x);
+        self.activation.apply(sum)
+    }
+}
+
+impl <'a>Module<'a> for Neuron<'a> {
+    fn named_parameters(&self) -> Vec<(String, Value<'a>)> {
+        self.weights.iter().enumerate()
+            .map(|(i, w)| (format!("w{}", i), *w))
+            .chain(std::iter::once(("b".to_string(), self.bias)))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Layer<'a> {
+    pub neurons: Vec<Neuron<'a>>,
+}
+
+impl <'a>Layer<'a> {
+    pub fn new(arena: &'a Arena, inputs: usize, outputs: usize, activation: Activation) -> Self {
+        Self {
+            neurons: (0..outputs).map(|_| Neuron::new(arena, inputs, activation)).collect(),
+        }
+    }
+
+    pub fn forward(&self, inputs: &[Value<'a>]) -> Vec<Value<'a>> {
+        self.neurons.iter().map(|n| n.forward(inputs)).collect()
+    }
+}
+
+impl <'a>Module<'a> for Layer<'a> {
+    fn named_parameters(&self) -> Vec<(String, Value<'a>)> {
+        self.neurons.iter().enumerate()
+            .flat_map(|(i, n)| nest(format!("neuron{}", i), n.named_parameters()))
+            .collect()
+    }
+}
+
+/// Multi-layer perceptron. Hidden layers use the given activation, the output layer is linear.
+#[derive(Debug, Clone)]
+pub struct MLP<'a> {
+    pub layers: Vec<Layer<'a>>,
+}
+
+impl <'a>MLP<'a> {
+    pub fn new(arena: &'a Arena, inputs: usize, layer_sizes: &[usize], activation: Activation) -> Self {
+        let sizes: Vec<usize> = std::iter::once(inputs).chain(layer_sizes.iter().copied()).collect();
+        Self {
+            layers: sizes.windows(2).enumerate()
+                .map(|(i, w)| Layer::new(arena, w[0], w[1], if i == layer_sizes.len() - 1 { Activation::Linear } else { activation }))
+                .collect(),
+        }
+    }
+
+    pub fn forward(&self, inputs: &[Value<'a>]) -> Vec<Value<'a>> {
+        self.layers.iter().fold(inputs.to_vec(), |x, layer| layer.forward(&x))
+    }
+}
+
+impl <'a>Module<'a> for MLP<'a> {
+    fn named_parameters(&self) -> Vec<(String, Value<'a>)> {
+        self.layers.iter().enumerate()
+            .flat_map(|(i, l)| nest(format!("layer{}", i), l.named_parameters()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::assert_close;
+
+    #[test]
+    fn parameter_count() {
+        let arena = Arena::new();
+        let mlp = MLP::new(&arena, 3, &[4, 4, 1], Activation::Tanh);
+        assert_eq!(mlp.parameters().len(), (3 * 4 + 4) + (4 * 4 + 4) + (4 + 1));
+        assert_eq!(mlp.layers[2].neurons[0].activation, Activation::Linear);
+    }
+
+    #[test]
+    fn named_parameters() {
+        let arena = Arena::new();
+        let mlp = MLP::new(&arena, 3, &[4, 2], Activation::Relu);
+        let named = mlp.named_parameters();
+        assert_eq!(named[0].0, "layer0.neuron0.w0");
+        assert_eq!(named[3].0, "layer0.neuron0.b");
+        assert_eq!(named.last().unwrap().0, "layer1.neuron1.b");
+        assert_eq!(named[5].1, mlp.layers[0].neurons[1].weights[1]);
+    }
+
+    #[test]
+    fn zero_grad_only_touches_parameters() {
+        let arena = Arena::new();
+        let neuron = Neuron::new(&arena, 2, Activation::Linear);
+        let x = [arena.value(1.0), arena.value(2.0)];
+        let out = neuron.forward(&x);
+        out.backward();
+        neuron.zero_grad();
+
+        assert!(neuron.parameters().iter().all(|p| p.grad() == 0.0));
+        assert_eq!(out.grad(), 1.0);
+        assert_eq!(x[1].grad(), neuron.weights[1].value());
+    }
+
+    #[test]
+    fn neuron_forward() {
+        let arena = Arena::new();
+        let neuron = Neuron::new(&arena, 2, Activation::Tanh);
+        neuron.bias.set_value(0.5);
+        let x = [arena.constant(1.0), arena.constant(-2.0)];
+        let out = neuron.forward(&x);
+
+        let expected = (neuron.weights[0].value() - 2.0 * neuron.weights[1].value() + 0.5).tanh();
+        assert_close(out.value(), expected);
+    }
+
+    #[test]
+    fn training_reduces_loss() {
+        let arena = Arena::new();
+        let mlp = MLP::new(&arena, 2, &[8, 1], Activation::Tanh);
+        let data = [([0.0, 1.0], 1.0), ([1.0, 0.0], -1.0), ([1.0, 1.0], 0.5)];
+        let checkpoint = arena.len();
+
+        let mut losses = vec![];
+        for _ in 0..50 {
+            arena.truncate(checkpoint);
+            let loss = data.iter()
+                .map(|(x, y)| (mlp.forward(&[arena.constant(x[0]), arena.constant(x[1])])[0] - *y).pow(2.0))
+                .reduce(|a, b| a + b)
+                .unwrap();
+            mlp.zero_grad();
+            loss.backward();
+            for p in mlp.parameters() {
+                p.apply_grad(0.05);
+            }
+            losses.push(loss.value());
+        }
+        assert!(losses.last().unwrap() < &losses[0]);
+    }
+}
@@ -0,0 +1,408 @@
+use std::collections::HashMap;
+
+use crate::Value;
+
+/// Updates a fixed set of parameters from their gradients, skipping any that aren't trainable
+pub trait Optimizer<'a> {
+    /// Apply one update using the current gradients
+    fn step(&mut self);
+
+    fn parameters(&self) -> &[Value<'a>];
+
+    fn learning_rate(&self) -> f64;
+
+    fn set_learning_rate(&mut self, learning_rate: f64);
+
+    fn zero_grad(&self) {
+        for p in self.parameters() {
+            p.set_grad(0.0);
+        }
+    }
+}
+
+/// Stochastic gradient descent with optional (Nesterov) momentum
+#[derive(Debug, Clone)]
+pub struct Sgd<'a> {
+    parameters: Vec<Value<'a>>,
+    learning_rate: f64,
+    momentum: f64,
+    nesterov: bool,
+    weight_decay: f64,
+    velocity: HashMap<Value<'a>, f64>,
+}
+
+impl <'a>Sgd<'a> {
+    pub fn new(parameters: Vec<Value<'a>>, learning_rate: f64) -> Self {
+        Self {
+            parameters,
+            learning_rate,
+            momentum: 0.0,
+            nesterov: false,
+            weight_decay: 0.0,
+            velocity: HashMap::new(),
+        }
+    }
+
+    pub fn momentum(mut self, momentum: f64) -> Self {
+        self.momentum = momentum;
+        self
+    }
+
+    pub fn nesterov(mut self, nesterov: bool) -> Self {
+        self.nesterov = nesterov;
+        self
+    }
+
+    pub fn weight_decay(mut self, weight_decay: f64) -> Self {
+        self.weight_decay = weight_decay;
+        self
+    }
+}
+
+impl <'a>Optimizer<'a> for Sgd<'a> {
+    fn step(&mut self) {
+        for p in self.parameters.iter().filter(|p| p.is_trainable()) {
+            let mut grad = p.grad() + self.weight_decay * p.value();
+            if self.momentum != 0.0 {
+                let velocity = self.velocity.entry(*p)
+                    .and_modify(|v| *v = self.momentum * *v + grad)
+                    .or_insert(grad);
+                grad = if self.nesterov { grad + self.momentum * *velocity } else { *velocity };
+            }
+            p.set_value(p.value() - self.learning_rate * grad);
+        }
+    }
+
+    fn parameters(&self) -> &[Value<'a>] {
+        &self.parameters
+    }
+
+    fn learning_rate(&self) -> f64 {
+        self.learning_rate
+    }
+
+    fn set_learning_rate(&mut self, learning_rate: f64) {
+        self.learning_rate = learning_rate;
+    }
+}
+
+/// Adam, or AdamW when weight decay is decoupled from the gradient
+#[derive(Debug, Clone)]
+pub struct Adam<'a> {
+    parameters: Vec<Value<'a>>,
+    learning_rate: f64,
+    betas: (f64, f64),
+    eps: f64,
+    weight_decay: f64,
+    decoupled_weight_decay: bool,
+    bias_correction: bool,
+    steps: i32,
+    moments: HashMap<Value<'a>, (f64, f64)>,
+}
+
+impl <'a>Adam<'a> {
+    pub fn new(parameters: Vec<Value<'a>>, learning_rate: f64) -> Self {
+        Self {
+            parameters,
+            learning_rate,
+            betas: (0.9, 0.999),
+            eps: 1e-8,
+            weight_decay: 0.0,
+            decoupled_weight_decay: false,
+            bias_correction: true,
+            steps: 0,
+            moments: HashMap::new(),
+        }
+    }
+
+    /// AdamW, with decoupled weight decay of 0.01
+    pub fn adamw(parameters: Vec<Value<'a>>, learning_rate: f64) -> Self {
+        Self::new(parameters, learning_rate)
+            .weight_decay(0.01)
+            .decoupled_weight_decay(true)
+    }
+
+    pub fn betas(mut self, beta1: f64, beta2: f64) -> Self {
+        self.betas = (beta1, beta2);
+        self
+    }
+
+    pub fn eps(mut self, eps: f64) -> Self {
+        self.eps = eps;
+        self
+    }
+
+    pub fn weight_decay(mut self, weight_decay: f64) -> Self {
+        self.weight_decay = weight_decay;
+        self
+    }
+
+    /// Decay the weights directly instead of adding the decay to the gradient
+    pub fn decoupled_weight_decay(mut self, decoupled: bool) -> Self {
+        self.decoupled_weight_decay = decoupled;
+        self
+    }
+
+    pub fn bias_correction(mut self, bias_correction: bool) -> Self {
+        self.bias_correction = bias_correction;
+        self
+    }
+}
+
+impl <'a>Optimizer<'a> for Adam<'a> {
+    fn step(&mut self) {
+        self.steps += 1;
+        let (beta1, beta2) = self.betas;
+        let (correction1, correction2) = if self.bias_correction {
+            (1.0 - beta1.powi(self.steps), 1.0 - beta2.powi(self.steps))
+        } else {
+            (1.0, 1.0)
+        };
+
+        for p in self.parameters.iter().filter(|p| p.is_trainable()) {
+            let mut value = p.value();
+            let mut grad = p.grad();
+            if self.decoupled_weight_decay {
+                value -= self.learning_rate * self.weight_decay * value;
+            } else {
+                grad += self.weight_decay * value;
+            }
+
+            let (m, v) = self.moments.entry(*p).or_insert((0.0, 0.0));
+            *m = beta1 * *m + (1.0 - beta1) * grad;
+            *v = beta2 * *v + (1.0 - beta2) * grad * grad;
+            value -= self.learning_rate * (*m / correction1) / ((*v / correction2).sqrt() + self.eps);
+            p.set_value(value);
+        }
+    }
+
+    fn parameters(&self) -> &[Value<'a>] {
+        &self.parameters
+    }
+
+    fn learning_rate(&self) -> f64 {
+        self.learning_rate
+    }
+
+    fn set_learning_rate(&mut self, learning_rate: f64) {
+        self.learning_rate = learning_rate;
+    }
+}
+
+/// RMSProp with optional momentum
+#[derive(Debug, Clone)]
+pub struct RmsProp<'a> {
+    parameters: Vec<Value<'a>>,
+    learning_rate: f64,
+    alpha: f64,
+    eps: f64,
+    momentum: f64,
+    weight_decay: f64,
+    square_avg: HashMap<Value<'a>, f64>,
+    velocity: HashMap<Value<'a>, f64>,
+}
+
+impl <'a>RmsProp<'a> {
+    pub fn new(parameters: Vec<Value<'a>>, learning_rate: f64) -> Self {
+        Self {
+            parameters,
+            learning_rate,
+            alpha: 0.99,
+            eps: 1e-8,
+            momentum: 0.0,
+            weight_decay: 0.0,
+            square_avg: HashMap::new(),
+            velocity: HashMap::new(),
+        }
+    }
+
+    /// Smoothing constant of the squared gradient average
+    pub fn alpha(mut self, alpha: f64) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    pub fn eps(mut self, eps: f64) -> Self {
+        self.eps = eps;
+        self
+    }
+
+    pub fn momentum(mut self, momentum: f64) -> Self {
+        self.momentum = momentum;
+        self
+    }
+
+    pub fn weight_decay(mut self, weight_decay: f64) -> Self {
+        self.weight_decay = weight_decay;
+        self
+    }
+}
+
+impl <'a>Optimizer<'a> for RmsProp<'a> {
+    fn step(&mut self) {
+        for p in self.parameters.iter().filter(|p| p.is_trainable()) {
+            let grad = p.grad() + self.weight_decay * p.value();
+            let square_avg = self.square_avg.entry(*p).or_insert(0.0);
+            *square_avg = self.alpha * *square_avg + (1.0 - self.alpha) * grad * grad;
+            let mut update = grad / (square_avg.sqrt() + self.eps);
+            if self.momentum != 0.0 {
+                let velocity = self.velocity.entry(*p).or_insert(0.0);
+                *velocity = self.momentum * *velocity + update;
+                update = *velocity;
+            }
+            p.set_value(p.value() - self.learning_rate * update);
+        }
+    }
+
+    fn parameters(&self) -> &[Value<'a>] {
+        &self.parameters
+    }
+
+    fn learning_rate(&self) -> f64 {
+        self.learning_rate
+    }
+
+    fn set_learning_rate(&mut self, learning_rate: f64) {
+        self.learning_rate = learning_rate;
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Adagrad<'a> {
+    parameters: Vec<Value<'a>>,
+    learning_rate: f64,
+    eps: f64,
+    weight_decay: f64,
+    sum: HashMap<Value<'a>, f64>,
+}
+
+impl <'a>Adagrad<'a> {
+    pub fn new(parameters: Vec<Value<'a>>, learning_rate: f64) -> Self {
+        Self {
+            parameters,
+            learning_rate,
+            eps: 1e-10,
+            weight_decay: 0.0,
+            sum: HashMap::new(),
+        }
+    }
+
+    pub fn eps(mut self, eps: f64) -> Self {
+        self.eps = eps;
+        self
+    }
+
+    pub fn weight_decay(mut self, weight_decay: f64) -> Self {
+        self.weight_decay = weight_decay;
+        self
+    }
+}
+
+impl <'a>Optimizer<'a> for Adagrad<'a> {
+    fn step(&mut self) {
+        for p in self.parameters.iter().filter(|p| p.is_trainable()) {
+            let grad = p.grad() + self.weight_decay * p.value();
+            let sum = self.sum.entry(*p).or_insert(0.0);
+            *sum += grad * grad;
+            p.set_value(p.value() - self.learning_rate * grad / (sum.sqrt() + self.eps));
+        }
+    }
+
+    fn parameters(&self) -> &[Value<'a>] {
+        &self.parameters
+    }
+
+    fn learning_rate(&self) -> f64 {
+        self.learning_rate
+    }
+
+    fn set_learning_rate(&mut self, learning_rate: f64) {
+        self.learning_rate = learning_rate;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_util::assert_close, Arena};
+
+    // Minimize (x - 3)^2 from x = 0
+    fn minimize<'a>(arena: &'a Arena, x: Value<'a>, optimizer: &mut dyn Optimizer<'a>, steps: usize) {
+        let checkpoint = arena.len();
+        for _ in 0..steps {
+            arena.truncate(checkpoint);
+            optimizer.zero_grad();
+            (x - 3.0).pow(2.0).backward();
+            optimizer.step();
+        }
+    }
+
+    #[test]
+    fn sgd_momentum() {
+        let arena = Arena::new();
+        let x = arena.value(1.0);
+        let mut sgd = Sgd::new(vec![x], 0.1).momentum(0.5);
+        x.set_grad(2.0);
+        sgd.step();
+        assert_close(x.value(), 1.0 - 0.1 * 2.0);
+        sgd.step();
+        assert_close(x.value(), 0.8 - 0.1 * (0.5 * 2.0 + 2.0));
+    }
+
+    #[test]
+    fn sgd_nesterov() {
+        let arena = Arena::new();
+        let x = arena.value(1.0);
+        let mut sgd = Sgd::new(vec![x], 0.1).momentum(0.5).nesterov(true);
+        x.set_grad(2.0);
+        sgd.step();
+        assert_close(x.value(), 1.0 - 0.1 * (2.0 + 0.5 * 2.0));
+    }
+
+    #[test]
+    fn adam_first_step_is_learning_rate() {
+        let arena = Arena::new();
+        let x = arena.value(1.0);
+        let mut adam = Adam::new(vec![x], 0.01);
+        x.set_grad(-5.0);
+        adam.step();
+        assert!((x.value() - 1.01).abs() < 1e-8);
+    }
+
+    #[test]
+    fn adamw_decays_without_gradient() {
+        let arena = Arena::new();
+        let x = arena.value(2.0);
+        let mut adamw = Adam::adamw(vec![x], 0.1);
+        adamw.step();
+        assert_close(x.value(), 2.0 - 0.1 * 0.01 * 2.0);
+    }
+
+    #[test]
+    fn optimizers_converge() {
+        let arena = Arena::new();
+        let params: Vec<Value> = (0..5).map(|_| arena.value(0.0)).collect();
+        let mut optimizers: Vec<Box<dyn Optimizer>> = vec![
+            Box::new(Sgd::new(vec![params[0]], 0.1).momentum(0.9)),
+            Box::new(Sgd::new(vec![params[1]], 0.1).momentum(0.9).nesterov(true)),
+            Box::new(Adam::new(vec![params[2]], 0.1)),
+            Box::new(RmsProp::new(vec![params[3]], 0.01)),
+            Box::new(Adagrad::new(vec![params[4]], 1.0)),
+        ];
+        for (x, optimizer) in params.iter().zip(optimizers.iter_mut()) {
+            minimize(&arena, *x, optimizer.as_mut(), 500);
+            assert!((x.value() - 3.0).abs() < 1e-2, "{}", x.value());
+        }
+    }
+
+    #[test]
+    fn frozen_parameters_are_skipped() {
+        let arena = Arena::new();
+        let x = arena.value(1.0);
+        x.set_requires_grad(false);
+        x.set_grad(2.0);
+        let mut adam = Adam::adamw(vec![x], 0.1);
+        adam.step();
+        assert_eq!(x.value(), 1.0);
+    }
+}
@@ -0,0 +1,100 @@
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+use crate::{Dual, Operation, Value};
+
+/// Number types supporting the full operator set. Functions written once over `S: Scalar`
+/// can be evaluated on `f64`, differentiated in reverse mode with [`Value`] or in forward mode with [`Dual`].
+pub trait Scalar:
+    Copy
+    + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self> + Div<Output = Self> + Neg<Output = Self>
+    + Add<f64, Output = Self> + Sub<f64, Output = Self> + Mul<f64, Output = Self> + Div<f64, Output = Self>
+{
+    /// A constant usable alongside `self`, e.g. in the same arena
+    fn constant(&self, value: f64) -> Self;
+    /// The plain number being carried
+    fn data(&self) -> f64;
+    fn pow(self, exponent: f64) -> Self;
+    fn exp(self) -> Self;
+    fn ln(self) -> Self;
+    fn sqrt(self) -> Self;
+    fn abs(self) -> Self;
+    fn tanh(self) -> Self;
+    fn relu(self) -> Self;
+    fn leaky_relu(self, slope: f64) -> Self;
+    fn sigmoid(self) -> Self;
+    fn gelu(self) -> Self;
+    fn softplus(self) -> Self;
+    fn elu(self, alpha: f64) -> Self;
+    fn silu(self) -> Self;
+    /// The same number, with no derivative flowing through it
+    fn detach(self) -> Self;
+}
+
+// Forward every method to the type's inherent implementation
+macro_rules! forward_scalar {
+    ($ty:ty) => {
+        fn pow(self, exponent: f64) -> Self { <$ty>::pow(self, exponent) }
+        fn exp(self) -> Self { <$ty>::exp(self) }
+        fn ln(self) -> Self { <$ty>::ln(self) }
+        fn sqrt(self) -> Self { <$ty>::sqrt(self) }
+        fn abs(self) -> Self { <$ty>::abs(self) }
+        fn tanh(self) -> Self { <$ty>::tanh(self) }
+        fn relu(self) -> Self { <$ty>::relu(self) }
+        fn leaky_relu(self, slope: f64) -> Self { <$ty>::leaky_relu(self, slope) }
+        fn sigmoid(self) -> Self { <$ty>::sigmoid(self) }
+        fn gelu(self) -> Self { <$ty>::gelu(self) }
+        fn softplus(self) -> Self { <$ty>::softplus(self) }
+        fn elu(self, alpha: f64) -> Self { <$ty>::elu(self, alpha) }
+        fn silu(self) -> Self { <$ty>::silu(self) }
+        fn detach(self) -> Self { <$ty>::detach(self) }
+    };
+}
+
+impl <'a>Scalar for Value<'a> {
+    fn constant(&self, value: f64) -> Self {
+        self.arena().constant(value)
+    }
+
+    fn data(&self) -> f64 {
+        self.value()
+    }
+
+    forward_scalar!(Value<'a>);
+}
+
+impl Scalar for Dual {
+    fn constant(&self, value: f64) -> Self {
+        Dual::constant(value)
+    }
+
+    fn data(&self) -> f64 {
+        self.value
+    }
+
+    forward_scalar!(Dual);
+}
+
+impl Scalar for f64 {
+    fn constant(&self, value: f64) -> Self {
+        value
+    }
+
+    fn data(&self) -> f64 {
+        *self
+    }
+
+    fn pow(self, exponent: f64) -> Self { Operation::Pow(exponent).apply(&[self]) }
+    fn exp(self) -> Self { Operation::Exp.apply(&[self]) }
+    fn ln(self) -> Self { Operation::Ln.apply(&[self]) }
+    fn sqrt(self) -> Self { Operation::Sqrt.apply(&[self]) }
+    fn abs(self) -> Self { Operation::Abs.apply(&[self]) }
+    fn tanh(self) -> Self { Operation::Tanh.apply(&[self]) }
+    fn relu(self) -> Self { Operation::Relu.apply(&[self]) }
+    fn leaky_relu(self, slope: f64) -> Self { Operation::LeakyRelu(slope).apply(&[self]) }
+    fn sigmoid(self) -> Self { Operation::Sigmoid.apply(&[self]) }
+    fn gelu(self) -> Self { Operation::Gelu.apply(&[self]) }
+    fn softplus(self) -> Self { Operation::Softplus.apply(&[self]) }
+    fn elu(self, alpha: f64) -> Self { Operation::Elu(alpha).apply(&[self]) }
+    fn silu(self) -> Self { Operation::Silu.apply(&[self]) }
+    fn detach(self) -> Self { self }
+}
@@ -0,0 +1,332 @@
+use std::f64::consts::PI;
+
+use crate::optim::Optimizer;
+
+/// Adjusts the learning rate over the course of training
+pub trait LrScheduler {
+    /// Learning rate for the current epoch or iteration
+    fn learning_rate(&self) -> f64;
+
+    /// Advance by one epoch or iteration
+    fn step(&mut self);
+
+    /// Set the optimizer's learning rate to the current one
+    fn apply<'a>(&self, optimizer: &mut dyn Optimizer<'a>) {
+        optimizer.set_learning_rate(self.learning_rate());
+    }
+}
+
+/// Multiply the rate by `gamma` every `step_size` steps
+#[derive(Debug, Clone)]
+pub struct StepDecay {
+    base: f64,
+    step_size: usize,
+    gamma: f64,
+    steps: usize,
+}
+
+impl StepDecay {
+    pub fn new(base: f64, step_size: usize, gamma: f64) -> Self {
+        Self { base, step_size, gamma, steps: 0 }
+    }
+}
+
+impl LrScheduler for StepDecay {
+    fn learning_rate(&self) -> f64 {
+        self.base * self.gamma.powi((self.steps / self.step_size.max(1)) as i32)
+    }
+
+    fn step(&mut self) {
+        self.steps += 1;
+    }
+}
+
+/// Multiply the rate by `gamma` every step
+#[derive(Debug, Clone)]
+pub struct Exponential {
+    base: f64,
+    gamma: f64,
+    steps: usize,
+}
+
+impl Exponential {
+    pub fn new(base: f64, gamma: f64) -> Self {
+        Self { base, gamma, steps: 0 }
+    }
+}
+
+impl LrScheduler for Exponential {
+    fn learning_rate(&self) -> f64 {
+        self.base * self.gamma.powi(self.steps as i32)
+    }
+
+    fn step(&mut self) {
+        self.steps += 1;
+    }
+}
+
+/// Anneal from `base` to `min` along a half cosine over `period` steps, then hold at `min`
+#[derive(Debug, Clone)]
+pub struct CosineAnnealing {
+    base: f64,
+    min: f64,
+    period: usize,
+    steps: usize,
+}
+
+impl CosineAnnealing {
+    pub fn new(base: f64, period: usize) -> Self {
+        Self { base, min: 0.0, period, steps: 0 }
+    }
+
+    pub fn min(mut self, min: f64) -> Self {
+        self.min = min;
+        self
+    }
+}
+
+impl LrScheduler for CosineAnnealing {
+    fn learning_rate(&self) -> f64 {
+        let progress = (self.steps as f64 / self.period.max(1) as f64).min(1.0);
+        self.min + (self.base - self.min) * (1.0 + (PI * progress).cos()) / 2.0
+    }
+
+    fn step(&mut self) {
+        self.steps += 1;
+    }
+}
+
+/// Ramp linearly from `base * start_factor` to `base` over `warmup` steps, then hold
+#[derive(Debug, Clone)]
+pub struct LinearWarmup {
+    base: f64,
+    warmup: usize,
+    start_factor: f64,
+    steps: usize,
+}
+
+impl LinearWarmup {
+    pub fn new(base: f64, warmup: usize) -> Self {
+        Self { base, warmup, start_factor: 0.0, steps: 0 }
+    }
+
+    pub fn start_factor(mut self, start_factor: f64) -> Self {
+        self.start_factor = start_factor;
+        self
+    }
+}
+
+impl LrScheduler for LinearWarmup {
+    fn learning_rate(&self) -> f64 {
+        let progress = (self.steps as f64 / self.warmup.max(1) as f64).min(1.0);
+        self.base * (self.start_factor + (1.0 - self.start_factor) * progress)
+    }
+
+    fn step(&mut self) {
+        self.steps += 1;
+    }
+}
+
+/// The one-cycle policy: cosine ramp from `max / div_factor` up to `max`,
+/// then cosine anneal down to `max / (div_factor * final_div_factor)`
+#[derive(Debug, Clone)]
+pub struct OneCycle {
+    max: f64,
+    total_steps: usize,
+    pct_start: f64,
+    div_factor: f64,
+    final_div_factor: f64,
+    steps: usize,
+}
+
+impl OneCycle {
+    pub fn new(max: f64, total_steps: usize) -> Self {
+        Self {
+            max,
+            total_steps,
+            pct_start: 0.3,
+            div_factor: 25.0,
+            final_div_factor: 1e4,
+            steps: 0,
+        }
+    }
+
+    /// Fraction of the cycle spent increasing the rate
+    pub fn pct_start(mut self, pct_start: f64) -> Self {
+        self.pct_start = pct_start;
+        self
+    }
+
+    pub fn div_factor(mut self, div_factor: f64) -> Self {
+        self.div_factor = div_factor;
+        self
+    }
+
+    pub fn final_div_factor(mut self, final_div_factor: f64) -> Self {
+        self.final_div_factor = final_div_factor;
+        self
+    }
+}
+
+impl LrScheduler for OneCycle {
+    fn learning_rate(&self) -> f64 {
+        let initial = self.max / self.div_factor;
+        let last = initial / self.final_div_factor;
+        let peak = (self.pct_start * self.total_steps as f64).max(1.0);
+        let step = self.steps.min(self.total_steps) as f64;
+        let anneal = |from: f64, to: f64, progress: f64| to + (from - to) * (1.0 + (PI * progress).cos()) / 2.0;
+        if step <= peak {
+            anneal(initial, self.max, step / peak)
+        } else {
+            anneal(self.max, last, (step - peak) / (self.total_steps as f64 - peak).max(1.0))
+        }
+    }
+
+    fn step(&mut self) {
+        self.steps += 1;
+    }
+}
+
+/// Multiply the rate by `factor` once a monitored metric stops improving for `patience` steps.
+/// Unlike the other schedulers this is stepped with the metric, see [`ReduceOnPlateau::step`].
+#[derive(Debug, Clone)]
+pub struct ReduceOnPlateau {
+    learning_rate: f64,
+    factor: f64,
+    patience: usize,
+    threshold: f64,
+    min: f64,
+    best: f64,
+    bad_steps: usize,
+}
+
+impl ReduceOnPlateau {
+    pub fn new(learning_rate: f64) -> Self {
+        Self {
+            learning_rate,
+            factor: 0.1,
+            patience: 10,
+            threshold: 1e-4,
+            min: 0.0,
+            best: f64::INFINITY,
+            bad_steps: 0,
+        }
+    }
+
+    pub fn factor(mut self, factor: f64) -> Self {
+        self.factor = factor;
+        self
+    }
+
+    pub fn patience(mut self, patience: usize) -> Self {
+        self.patience = patience;
+        self
+    }
+
+    /// Relative improvement required to count as better
+    pub fn threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    pub fn min(mut self, min: f64) -> Self {
+        self.min = min;
+        self
+    }
+
+    pub fn learning_rate(&self) -> f64 {
+        self.learning_rate
+    }
+
+    /// Record a metric where lower is better, e.g. the validation loss
+    pub fn step(&mut self, metric: f64) {
+        if metric < self.best * (1.0 - self.threshold) {
+            self.best = metric;
+            self.bad_steps = 0;
+        } else {
+            self.bad_steps += 1;
+            if self.bad_steps > self.patience {
+                self.learning_rate = (self.learning_rate * self.factor).max(self.min);
+                self.bad_steps = 0;
+            }
+        }
+    }
+
+    pub fn apply<'a>(&self, optimizer: &mut dyn Optimizer<'a>) {
+        optimizer.set_learning_rate(self.learning_rate);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{optim::Sgd, test_util::assert_close, Arena};
+
+    fn rates(scheduler: &mut dyn LrScheduler, steps: usize) -> Vec<f64> {
+        (0..steps).map(|_| {
+            let lr = scheduler.learning_rate();
+            scheduler.step();
+            lr
+        }).collect()
+    }
+
+    #[test]
+    fn step_and_exponential_decay() {
+        assert_eq!(rates(&mut StepDecay::new(1.0, 2, 0.5), 5), vec![1.0, 1.0, 0.5, 0.5, 0.25]);
+        let exponential = rates(&mut Exponential::new(2.0, 0.5), 3);
+        assert_eq!(exponential, vec![2.0, 1.0, 0.5]);
+    }
+
+    #[test]
+    fn cosine_annealing() {
+        let lrs = rates(&mut CosineAnnealing::new(1.0, 4).min(0.2), 6);
+        assert_close(lrs[0], 1.0);
+        assert_close(lrs[2], 0.6);
+        assert_close(lrs[4], 0.2);
+        assert_close(lrs[5], 0.2);
+    }
+
+    #[test]
+    fn zero_periods() {
+        assert_eq!(rates(&mut StepDecay::new(1.0, 0, 0.5), 3), vec![1.0, 0.5, 0.25]);
+        assert_eq!(rates(&mut CosineAnnealing::new(1.0, 0).min(0.2), 2), vec![1.0, 0.2]);
+    }
+
+    #[test]
+    fn linear_warmup() {
+        let lrs = rates(&mut LinearWarmup::new(1.0, 4), 6);
+        assert_eq!(lrs, vec![0.0, 0.25, 0.5, 0.75, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn one_cycle() {
+        let lrs = rates(&mut OneCycle::new(1.0, 10), 11);
+        assert_close(lrs[0], 1.0 / 25.0);
+        assert_close(lrs[3], 1.0);
+        assert_close(lrs[10], 1.0 / 25.0 / 1e4);
+        assert!(lrs[..4].windows(2).all(|w| w[0] < w[1]));
+        assert!(lrs[3..].windows(2).all(|w| w[0] > w[1]));
+    }
+
+    #[test]
+    fn reduce_on_plateau() {
+        let mut scheduler = ReduceOnPlateau::new(1.0).patience(1).factor(0.5);
+        for metric in [3.0, 2.0, 2.0, 2.0] {
+            scheduler.step(metric);
+        }
+        assert_close(scheduler.learning_rate(), 0.5);
+        scheduler.step(1.0);
+        scheduler.step(1.0);
+        assert_close(scheduler.learning_rate(), 0.5);
+    }
+
+    #[test]
+    fn applies_to_optimizer() {
+        let arena = Arena::new();
+        let mut sgd = Sgd::new(vec![arena.value(0.0)], 1.0);
+        let mut scheduler = Exponential::new(1.0, 0.1);
+        scheduler.step();
+        scheduler.apply(&mut sgd);
+        assert_close(sgd.learning_rate(), 0.1);
+    }
+}
@@ -0,0 +1,5 @@
+// Helpers shared by the unit tests
+
+pub(crate) fn assert_close(a: f64, b: f64) {
+    assert!((a - b).abs() < 1e-9, "{} != {}", a, b);
+}
@@ -0,0 +1,159 @@
+use std::{collections::HashSet, fmt::Write};
+
+use crate::Value;
+
+// Terminal tree rendering
+impl <'a>Value<'a> {
+    /// Draw the expression tree ending at this value with box-drawing characters.
+    /// A shared value is expanded where it first appears and referred back to by id afterwards.
+    /// The output grows with depth times size, so prefer [`Value::pretty_tree_depth`] for big graphs.
+    pub fn pretty_tree(&self) -> String {
+        self.render_tree(usize::MAX)
+    }
+
+    /// Like [`Value::pretty_tree`], but only `max_depth` levels below this value
+    pub fn pretty_tree_depth(&self, max_depth: usize) -> String {
+        self.render_tree(max_depth)
+    }
+
+    fn render_tree(&self, max_depth: usize) -> String {
+        // Lay out every line first, so values drawn again can be tagged where they first appear
+        let mut lines: Vec<TreeLine> = vec![];
+        let mut expanded = HashSet::new();
+        let mut repeated = HashSet::new();
+        // Each entry holds the prefix of its own line and the prefix of its children's lines
+        let mut stack = vec![(*self, String::new(), String::new(), 0)];
+        while let Some((node, line_prefix, child_prefix, depth)) = stack.pop() {
+            let children = node.children();
+            // Leaves are cheap to draw again
+            let repeat = !children.is_empty() && !expanded.insert(node.id());
+            if repeat {
+                repeated.insert(node.id());
+            }
+            lines.push(TreeLine { prefix: line_prefix, node: Some(node), repeat });
+            if repeat || children.is_empty() {
+                continue;
+            }
+            if depth == max_depth {
+                lines.push(TreeLine { prefix: format!("{}└── ", child_prefix), node: None, repeat: false });
+                continue;
+            }
+            // Pushed in reverse so they come off the stack in order
+            for (i, child) in children.iter().enumerate().rev() {
+                let last = i == children.len() - 1;
+                stack.push((
+                    *child,
+                    format!("{}{}", child_prefix, if last { "└── " } else { "├── " }),
+                    format!("{}{}", child_prefix, if last { "    " } else { "│   " }),
+                    depth + 1,
+                ));
+            }
+        }
+
+        let mut out = String::new();
+        for line in lines {
+            match line.node {
+                None => writeln!(out, "{}…", line.prefix),
+                Some(node) if line.repeat => writeln!(out, "{}{} [#{}, see above]", line.prefix, node.tree_line(), node.id()),
+                Some(node) if repeated.contains(&node.id()) => writeln!(out, "{}{} [#{}]", line.prefix, node.tree_line(), node.id()),
+                Some(node) => writeln!(out, "{}{}", line.prefix, node.tree_line()),
+            }.unwrap();
+        }
+        out
+    }
+
+    fn tree_line(&self) -> String {
+        let label = self.get_label();
+        let mut line = if label.is_empty() { String::new() } else { format!("{} | ", label) };
+        write!(line, "data: {} | grad: {}", self.value(), self.grad()).unwrap();
+        if let Some(operation) = self.operation() {
+            write!(line, " ({})", operation).unwrap();
+        }
+        line
+    }
+}
+
+// One line of a drawn tree, either a value or, without one, a truncation marker
+struct TreeLine<'a> {
+    prefix: String,
+    node: Option<Value<'a>>,
+    repeat: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{value::DISPLAY_DEPTH, Arena};
+
+    #[test]
+    fn draws_tree() {
+        let arena = Arena::new();
+        let a = arena.value(2.0).label("a");
+        let b = arena.value(-3.0).label("b");
+        let c = arena.value(10.0).label("c");
+        let out = (c + a * b).tanh().label("out");
+
+        let expected = "\
+out | data: 0.999329299739067 | grad: 0 (tanh)
+└── data: 4 | grad: 0 (+)
+    ├── c | data: 10 | grad: 0
+    └── data: -6 | grad: 0 (*)
+        ├── a | data: 2 | grad: 0
+        └── b | data: -3 | grad: 0
+";
+        assert_eq!(out.pretty_tree(), expected);
+        assert_eq!(format!("{:#}", out), expected);
+    }
+
+    #[test]
+    fn truncates_depth() {
+        let arena = Arena::new();
+        let a = arena.value(1.0).label("a");
+        let out = (a * a + a).label("out");
+
+        let expected = "\
+out | data: 2 | grad: 0 (+)
+├── data: 1 | grad: 0 (*)
+│   └── …
+└── a | data: 1 | grad: 0
+";
+        assert_eq!(out.pretty_tree_depth(1), expected);
+    }
+
+    #[test]
+    fn shared_values_drawn_once() {
+        let arena = Arena::new();
+        let a = arena.value(2.0).label("a");
+        let b = (a * a).label("b");
+        let out = (b + b).label("out");
+
+        let expected = "\
+out | data: 8 | grad: 0 (+)
+├── b | data: 4 | grad: 0 (*) [#1]
+│   ├── a | data: 2 | grad: 0
+│   └── a | data: 2 | grad: 0
+└── b | data: 4 | grad: 0 (*) [#1, see above]
+";
+        assert_eq!(out.pretty_tree(), expected);
+    }
+
+    #[test]
+    fn deep_and_wide_graphs() {
+        let arena = Arena::new();
+        let x = arena.value(1.0);
+        // Repeated squaring would draw 2^n leaves without sharing
+        let mut out = x;
+        for _ in 0..64 {
+            out = out * out;
+        }
+        // Each product is expanded once and referred back to once, above the two leaves
+        assert_eq!(out.pretty_tree().lines().count(), 64 + 63 + 2);
+
+        // A long sum like `loss::mean` builds over a dataset
+        let mut sum = x;
+        for _ in 0..200_000 {
+            sum = sum + x;
+        }
+        // One sum and one `x` per level, then the sum where drawing stops and its marker
+        assert_eq!(format!("{:#}", sum).lines().count(), 2 * DISPLAY_DEPTH + 2);
+    }
+}
@@ -1,168 +1,1257 @@
-use std::{fmt::Display, ops::{Add, Mul}};
+use std::{cell::RefCell, collections::{HashMap, HashSet}, rc::Rc, fmt::{Debug, Display}, hash::{Hash, Hasher}, ops::{Add, Div, Mul, Neg, Sub}};
 
-use petgraph::{Graph, Directed, matrix_graph::NodeIndex, dot::{Dot, Config}};
+use crate::{custom, AutogradError, CustomOpId, Scalar};
 
-#[derive(Debug)]
-pub struct Value<'a> {
-    pub value: f64,
-    pub grad: f64,
-    children: Option<Vec<&'a mut Value<'a>>>,
+/// Owns every node of a computation graph. Values are cheap `Copy` handles into an arena,
+/// so the same value can be used any number of times in an expression.
+#[derive(Debug, Default)]
+pub struct Arena {
+    nodes: RefCell<Vec<Node>>,
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    value: f64,
+    grad: f64,
+    children: Vec<usize>,
     operation: Option<Operation>,
     label: String,
+    grad_fn: Option<GradFn>,
+    requires_grad: bool,
+}
+
+/// Gradient rule set by [`Value::custom_grad`], taking the inputs, output and upstream gradient
+type GradRule = dyn Fn(&[f64], f64, f64) -> Vec<f64>;
+
+#[derive(Clone)]
+struct GradFn(Rc<GradRule>);
+
+impl Debug for GradFn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "GradFn")
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Clone, Copy)]
+pub struct Value<'a> {
+    arena: &'a Arena,
+    id: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Operation {
     Add,
+    Sub,
     Mul,
-    Tanh
+    Div,
+    Neg,
+    Pow(f64),
+    Exp,
+    Ln,
+    Sqrt,
+    Abs,
+    Tanh,
+    Relu,
+    LeakyRelu(f64),
+    Sigmoid,
+    Gelu,
+    Softplus,
+    Elu(f64),
+    Silu,
+    /// Passes its input through but blocks gradients, see [`Value::detach`]
+    Detach,
+    /// A user-defined operation, see [`register_op`](crate::register_op)
+    Custom(CustomOpId),
+    /// Output of a subgraph wrapped by [`Value::custom_grad`]. Takes the given number of
+    /// wrapped inputs followed by the subgraph's result, which it passes through.
+    CustomGrad(usize),
 }
 
-impl ToString for Operation {
-    fn to_string(&self) -> String {
+impl Display for Operation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Operation::Add => "+",
-            Operation::Mul => "-",
-            Operation::Tanh => "tanh",
-        }.to_string()
+            Operation::Add => write!(f, "+"),
+            Operation::Sub => write!(f, "-"),
+            Operation::Mul => write!(f, "*"),
+            Operation::Div => write!(f, "/"),
+            Operation::Neg => write!(f, "neg"),
+            Operation::Pow(exponent) => write!(f, "^{}", exponent),
+            Operation::Exp => write!(f, "exp"),
+            Operation::Ln => write!(f, "ln"),
+            Operation::Sqrt => write!(f, "sqrt"),
+            Operation::Abs => write!(f, "abs"),
+            Operation::Tanh => write!(f, "tanh"),
+            Operation::Relu => write!(f, "relu"),
+            Operation::LeakyRelu(slope) => write!(f, "leaky_relu({})", slope),
+            Operation::Sigmoid => write!(f, "sigmoid"),
+            Operation::Gelu => write!(f, "gelu"),
+            Operation::Softplus => write!(f, "softplus"),
+            Operation::Elu(alpha) => write!(f, "elu({})", alpha),
+            Operation::Silu => write!(f, "silu"),
+            Operation::Detach => write!(f, "detach"),
+            Operation::Custom(id) => write!(f, "{}", custom::lookup(*id).name()),
+            Operation::CustomGrad(_) => write!(f, "custom_grad"),
+        }
     }
 }
 
-impl <'a>Value<'a> {
-    pub fn new(value: f64) -> Self {
-        Self {
-            value, 
-            grad: 0.0,
-            children: None,
-            operation: None,
-            label: "".to_string()
+const GELU_COEFF: f64 = 0.044715;
+const SQRT_2_OVER_PI: f64 = 0.7978845608028654;
+
+fn sigmoid(x: f64) -> f64 {
+    // Split on sign so exp never overflows
+    if x >= 0.0 {
+        1.0 / (1.0 + (-x).exp())
+    } else {
+        let e = x.exp();
+        e / (1.0 + e)
+    }
+}
+
+impl Operation {
+    /// Number of inputs the operation takes
+    pub fn arity(&self) -> usize {
+        match self {
+            Operation::Add | Operation::Sub | Operation::Mul | Operation::Div => 2,
+            Operation::Custom(id) => custom::lookup(*id).arity(),
+            Operation::CustomGrad(inputs) => inputs + 1,
+            _ => 1,
         }
     }
 
-    fn children(mut self, children: Vec<&'a mut Value<'a>>) -> Self {
-        self.children = Some(children);
-        self
+    /// Evaluate the operation on its inputs
+    pub fn apply(&self, inputs: &[f64]) -> f64 {
+        match *self {
+            Operation::Add => inputs[0] + inputs[1],
+            Operation::Sub => inputs[0] - inputs[1],
+            Operation::Mul => inputs[0] * inputs[1],
+            Operation::Div => inputs[0] / inputs[1],
+            Operation::Neg => -inputs[0],
+            Operation::Pow(exponent) => inputs[0].powf(exponent),
+            Operation::Exp => inputs[0].exp(),
+            Operation::Ln => inputs[0].ln(),
+            Operation::Sqrt => inputs[0].sqrt(),
+            Operation::Abs => inputs[0].abs(),
+            Operation::Tanh => inputs[0].tanh(),
+            Operation::Relu => inputs[0].max(0.0),
+            Operation::LeakyRelu(slope) => if inputs[0] > 0.0 { inputs[0] } else { slope * inputs[0] },
+            Operation::Sigmoid => sigmoid(inputs[0]),
+            Operation::Gelu => {
+                let x = inputs[0];
+                0.5 * x * (1.0 + (SQRT_2_OVER_PI * (x + GELU_COEFF * x.powi(3))).tanh())
+            },
+            Operation::Softplus => inputs[0].max(0.0) + (-inputs[0].abs()).exp().ln_1p(),
+            Operation::Elu(alpha) => if inputs[0] > 0.0 { inputs[0] } else { alpha * inputs[0].exp_m1() },
+            Operation::Silu => inputs[0] * sigmoid(inputs[0]),
+            Operation::Detach => inputs[0],
+            Operation::Custom(id) => custom::lookup(id).forward(inputs),
+            Operation::CustomGrad(wrapped) => inputs[wrapped],
+        }
     }
 
-    fn operation(mut self, operation: Operation) -> Self {
-        self.operation = Some(operation);
-        self
+    /// Partial derivative of the output with respect to each input. Generic so the same rules
+    /// give plain numbers or, on `Value`s, new graph nodes that can be differentiated again.
+    /// Custom operations only provide numeric gradients, so theirs are constants and
+    /// differentiating them a second time gives zero.
+    pub fn derivatives<S: Scalar>(&self, inputs: &[S], output: S) -> Vec<S> {
+        let constant = |value: f64| output.constant(value);
+        match *self {
+            Operation::Add => vec![constant(1.0), constant(1.0)],
+            Operation::Sub => vec![constant(1.0), constant(-1.0)],
+            Operation::Mul => vec![inputs[1], inputs[0]],
+            Operation::Div => vec![constant(1.0) / inputs[1], -(inputs[0] / (inputs[1] * inputs[1]))],
+            Operation::Neg => vec![constant(-1.0)],
+            // x^0 is constant, and x^-1 * 0 would be NaN at x = 0
+            Operation::Pow(0.0) => vec![constant(0.0)],
+            Operation::Pow(exponent) => vec![inputs[0].pow(exponent - 1.0) * exponent],
+            Operation::Exp => vec![output],
+            Operation::Ln => vec![constant(1.0) / inputs[0]],
+            Operation::Sqrt => vec![constant(0.5) / output],
+            // Subgradient of 0 at x = 0
+            Operation::Abs => vec![constant(if inputs[0].data() == 0.0 { 0.0 } else { inputs[0].data().signum() })],
+            Operation::Tanh => vec![constant(1.0) - output * output],
+            // Subgradient of 0 at x = 0
+            Operation::Relu => vec![constant(if inputs[0].data() > 0.0 { 1.0 } else { 0.0 })],
+            Operation::LeakyRelu(slope) => vec![constant(if inputs[0].data() > 0.0 { 1.0 } else { slope })],
+            Operation::Sigmoid => vec![output - output * output],
+            Operation::Gelu => {
+                let x = inputs[0];
+                let t = ((x + x.pow(3.0) * GELU_COEFF) * SQRT_2_OVER_PI).tanh();
+                let dt = (constant(1.0) - t * t) * (x * x * (3.0 * GELU_COEFF * SQRT_2_OVER_PI) + SQRT_2_OVER_PI);
+                vec![(t + 1.0) * 0.5 + x * dt * 0.5]
+            },
+            Operation::Softplus => vec![inputs[0].sigmoid()],
+            // For x <= 0 the derivative alpha * e^x equals output + alpha
+            Operation::Elu(alpha) => vec![if inputs[0].data() > 0.0 { constant(1.0) } else { output + alpha }],
+            Operation::Silu => {
+                let s = inputs[0].sigmoid();
+                vec![s + inputs[0] * (s - s * s)]
+            },
+            Operation::Detach => vec![constant(0.0)],
+            Operation::Custom(id) => {
+                let values: Vec<f64> = inputs.iter().map(|x| x.data()).collect();
+                let grads = custom::lookup(id).backward(&values, output.data(), 1.0);
+                assert_eq!(grads.len(), values.len(), "{} returned the wrong number of gradients", self);
+                grads.into_iter().map(constant).collect()
+            },
+            // The rule lives on the node, see `Value::custom_grad`
+            Operation::CustomGrad(wrapped) => vec![constant(0.0); wrapped + 1],
+        }
+    }
+
+    /// Gradient with respect to each input given the gradient `grad` of the output,
+    /// as used by [`Value::backward`]
+    pub fn vjp(&self, inputs: &[f64], output: f64, grad: f64) -> Vec<f64> {
+        match *self {
+            Operation::Custom(id) => custom::lookup(id).backward(inputs, output, grad),
+            _ => self.derivatives(inputs, output).into_iter().map(|derivative| grad * derivative).collect(),
+        }
     }
 
-   pub fn label<T: ToString>(mut self, label: T) -> Self {
-        self.label = label.to_string();
+    /// Whether gradients flow back into the input in `slot` at all
+    pub(crate) fn differentiable(&self, slot: usize) -> bool {
+        match *self {
+            Operation::Detach => false,
+            // The wrapped subgraph's result is passed through without being differentiated
+            Operation::CustomGrad(wrapped) => slot < wrapped,
+            _ => true,
+        }
+    }
+}
+
+impl Arena {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a new leaf value
+    pub fn value(&self, value: f64) -> Value<'_> {
+        self.push(value, vec![], None)
+    }
+
+    /// Create a non-trainable constant leaf, labeled by its value
+    pub fn constant(&self, value: f64) -> Value<'_> {
+        let constant = self.value(value).label(value);
+        constant.set_requires_grad(false);
+        constant
+    }
+
+    /// Number of nodes in the arena
+    pub fn len(&self) -> usize {
+        self.nodes.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.borrow().is_empty()
+    }
+
+    /// Reset the gradient of every node
+    pub fn zero_grad(&self) {
+        for node in self.nodes.borrow_mut().iter_mut() {
+            node.grad = 0.0;
+        }
+    }
+
+    /// Drop every node created after the first `len`, e.g. the graph of a finished training step.
+    /// Handles to dropped nodes must not be used afterwards.
+    pub fn truncate(&self, len: usize) {
+        self.nodes.borrow_mut().truncate(len);
+    }
+
+    fn push(&self, value: f64, children: Vec<usize>, operation: Option<Operation>) -> Value<'_> {
+        let mut nodes = self.nodes.borrow_mut();
+        nodes.push(Node {
+            value,
+            grad: 0.0,
+            children,
+            operation,
+            label: "".to_string(),
+            grad_fn: None,
+            requires_grad: true,
+        });
+        Value { arena: self, id: nodes.len() - 1 }
+    }
+}
+
+impl <'a>Value<'a> {
+    fn from_op(children: &[Value<'a>], operation: Operation) -> Self {
+        let arena = children[0].arena;
+        assert!(children.iter().all(|c| std::ptr::eq(c.arena, arena)), "values belong to different arenas");
+        let inputs: Vec<f64> = children.iter().map(|c| c.value()).collect();
+        // With the wrong number of inputs there is nothing to evaluate, `validate` reports it instead
+        let value = if inputs.len() == operation.arity() { operation.apply(&inputs) } else { f64::NAN };
+        arena.push(value, children.iter().map(|c| c.id).collect(), Some(operation))
+    }
+
+    /// Apply any operation, built-in or registered with [`register_op`](crate::register_op), to `inputs`.
+    /// With the wrong number of inputs the result is NaN and [`Value::validate`] reports the mismatch.
+    pub fn apply(operation: Operation, inputs: &[Value<'a>]) -> Self {
+        assert!(!inputs.is_empty(), "{} applied to no inputs", operation);
+        assert!(!matches!(operation, Operation::CustomGrad(_)), "use Value::custom_grad to wrap a subgraph");
+        Value::from_op(inputs, operation)
+    }
+
+    pub fn label<T: ToString>(self, label: T) -> Self {
+        self.arena.nodes.borrow_mut()[self.id].label = label.to_string();
         self
     }
+
+    pub fn get_label(&self) -> String {
+        self.arena.nodes.borrow()[self.id].label.clone()
+    }
+
+    pub fn value(&self) -> f64 {
+        self.arena.nodes.borrow()[self.id].value
+    }
+
+    pub fn set_value(&self, value: f64) {
+        self.arena.nodes.borrow_mut()[self.id].value = value;
+    }
+
+    pub fn grad(&self) -> f64 {
+        self.arena.nodes.borrow()[self.id].grad
+    }
+
+    pub fn set_grad(&self, grad: f64) {
+        self.arena.nodes.borrow_mut()[self.id].grad = grad;
+    }
+
+    /// Whether `backward` computes a gradient for this value: true for leaves unless turned off,
+    /// and for results depending on such a leaf other than through [`Value::detach`]
+    pub fn requires_grad(&self) -> bool {
+        if self.is_leaf() {
+            return self.arena.nodes.borrow()[self.id].requires_grad;
+        }
+        self.requires_grad_mask(&self.topological_order())[self.id]
+    }
+
+    /// Whether this is a leaf rather than the result of an operation
+    pub fn is_leaf(&self) -> bool {
+        self.arena.nodes.borrow()[self.id].operation.is_none()
+    }
+
+    /// A leaf that requires grad, i.e. something an optimizer should update. Unlike
+    /// [`Value::requires_grad`] this never walks the graph.
+    pub fn is_trainable(&self) -> bool {
+        let node = &self.arena.nodes.borrow()[self.id];
+        node.operation.is_none() && node.requires_grad
+    }
+
+    /// Mark a leaf as trainable or not. Use [`Value::detach`] to stop gradients elsewhere.
+    pub fn set_requires_grad(&self, requires_grad: bool) {
+        assert!(self.is_leaf(), "requires_grad can only be set on leaves, detach the value instead");
+        self.arena.nodes.borrow_mut()[self.id].requires_grad = requires_grad;
+    }
+
+    pub fn operation(&self) -> Option<Operation> {
+        self.arena.nodes.borrow()[self.id].operation
+    }
+
+    pub fn children(&self) -> Vec<Value<'a>> {
+        self.arena.nodes.borrow()[self.id].children.iter()
+            .map(|&id| Value { arena: self.arena, id })
+            .collect()
+    }
+
+    /// Index of this value in its arena
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// The arena this value lives in
+    pub fn arena(&self) -> &'a Arena {
+        self.arena
+    }
+}
+
+impl <'a>PartialEq for Value<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self.arena, other.arena) && self.id == other.id
+    }
+}
+
+impl <'a>Eq for Value<'a> {}
+
+impl <'a>Hash for Value<'a> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::ptr::hash(self.arena, state);
+        self.id.hash(state);
+    }
+}
+
+impl <'a>Debug for Value<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let node = &self.arena.nodes.borrow()[self.id];
+        f.debug_struct("Value")
+            .field("id", &self.id)
+            .field("value", &node.value)
+            .field("grad", &node.grad)
+            .field("operation", &node.operation)
+            .field("label", &node.label)
+            .finish()
+    }
 }
 
+/// Levels of the expression tree drawn by `{:#}`
+pub(crate) const DISPLAY_DEPTH: usize = 16;
+
 impl <'a>Display for Value<'a> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "Value {{ label: {} data: {} grad: {}}}", self.label, self.value, self.grad)
+        // `{:#}` draws the expression tree, as deep as fits on a screen
+        if f.alternate() {
+            return write!(f, "{}", self.pretty_tree_depth(DISPLAY_DEPTH));
+        }
+        writeln!(f, "Value {{ label: {} data: {} grad: {}}}", self.get_label(), self.value(), self.grad())
     }
 }
 
 // Operators
-impl <'a>Add<&'a mut Value<'a>> for &'a mut Value<'a> {
+impl <'a>Add for Value<'a> {
+    type Output = Value<'a>;
+
+    fn add(self, rhs: Value<'a>) -> Self::Output {
+        Value::from_op(&[self, rhs], Operation::Add)
+    }
+}
+
+impl <'a>Mul for Value<'a> {
+    type Output = Value<'a>;
+
+    fn mul(self, rhs: Value<'a>) -> Self::Output {
+        Value::from_op(&[self, rhs], Operation::Mul)
+    }
+}
+
+impl <'a>Sub for Value<'a> {
+    type Output = Value<'a>;
+
+    fn sub(self, rhs: Value<'a>) -> Self::Output {
+        Value::from_op(&[self, rhs], Operation::Sub)
+    }
+}
+
+impl <'a>Div for Value<'a> {
     type Output = Value<'a>;
 
-    fn add(self, rhs: &'a mut Value<'a>) -> Self::Output {
-        Value::new(self.value + rhs.value)
-            .children(vec![self, rhs])
-            .operation(Operation::Add)
+    fn div(self, rhs: Value<'a>) -> Self::Output {
+        Value::from_op(&[self, rhs], Operation::Div)
     }
 }
 
-impl <'a>Mul<&'a mut Value<'a>> for &'a mut Value<'a> {
+impl <'a>Neg for Value<'a> {
     type Output = Value<'a>;
 
-    fn mul(self, rhs: &'a mut Value<'a>) -> Self::Output {
-        Value::new(self.value * rhs.value)
-            .children(vec![self, rhs])
-            .operation(Operation::Mul)
+    fn neg(self) -> Self::Output {
+        Value::from_op(&[self], Operation::Neg)
     }
 }
 
+// Mixed scalar arithmetic, wrapping the scalar in a constant leaf
+macro_rules! scalar_op {
+    ($op:ident, $method:ident) => {
+        impl <'a>$op<f64> for Value<'a> {
+            type Output = Value<'a>;
+
+            fn $method(self, rhs: f64) -> Self::Output {
+                self.$method(self.arena.constant(rhs))
+            }
+        }
+
+        impl <'a>$op<Value<'a>> for f64 {
+            type Output = Value<'a>;
+
+            fn $method(self, rhs: Value<'a>) -> Self::Output {
+                rhs.arena.constant(self).$method(rhs)
+            }
+        }
+    };
+}
+
+scalar_op!(Add, add);
+scalar_op!(Sub, sub);
+scalar_op!(Mul, mul);
+scalar_op!(Div, div);
+
 impl <'a>Value<'a> {
-    pub fn tanh(&'a mut self) -> Value {
-        Value::new(self.value.tanh())
-            .children(vec![self])
-            .operation(Operation::Tanh)
+    pub fn pow(self, exponent: f64) -> Value<'a> {
+        Value::from_op(&[self], Operation::Pow(exponent))
+    }
+
+    pub fn exp(self) -> Value<'a> {
+        Value::from_op(&[self], Operation::Exp)
+    }
+
+    pub fn ln(self) -> Value<'a> {
+        Value::from_op(&[self], Operation::Ln)
+    }
+
+    pub fn sqrt(self) -> Value<'a> {
+        Value::from_op(&[self], Operation::Sqrt)
+    }
+
+    pub fn abs(self) -> Value<'a> {
+        Value::from_op(&[self], Operation::Abs)
+    }
+
+    pub fn tanh(self) -> Value<'a> {
+        Value::from_op(&[self], Operation::Tanh)
     }
 }
 
-// Graph
+// Activations
 impl <'a>Value<'a> {
-    pub fn graph(&self) {
-        let mut graph = Graph::default();
-        let start = graph.add_node(format!("{} | data: {} | grad: {}", self.label, self.value, self.grad));
-        self.inner_graph(start, &mut graph);
+    pub fn relu(self) -> Value<'a> {
+        Value::from_op(&[self], Operation::Relu)
+    }
 
-        let url = format!("https://dreampuf.github.io/GraphvizOnline/#{}", urlencoding::encode(&Dot::with_config(&graph, &[Config::EdgeNoLabel]).to_string()));
-        if let Err(e) = webbrowser::open(&url) {
-            println!("Error displaying graph: {:?}", e);
-        }
+    pub fn leaky_relu(self, slope: f64) -> Value<'a> {
+        Value::from_op(&[self], Operation::LeakyRelu(slope))
     }
 
-    fn inner_graph(&self, curr_node: NodeIndex<u32>, graph: &mut Graph<String, bool, Directed, u32>) {
-        if let Some(children) = &self.children {
-            // Make new nodes for children
-            let op_node = graph.add_node(self.operation.unwrap().to_string());
-            let nodes: Vec<petgraph::stable_graph::NodeIndex> = children.iter()
-                .map(|child| graph.add_node(format!("{} | data: {} | grad: {}", child.label, child.value, child.grad)))
-                .collect();
+    pub fn sigmoid(self) -> Value<'a> {
+        Value::from_op(&[self], Operation::Sigmoid)
+    }
 
-            // Make edges
-            graph.add_edge(op_node, curr_node, false);
-            for node in &nodes {
-                graph.add_edge(*node, op_node, false);
-            }
+    /// GELU using the tanh approximation
+    pub fn gelu(self) -> Value<'a> {
+        Value::from_op(&[self], Operation::Gelu)
+    }
 
-            // Run on child nodes
-            for (child, node) in children.iter().zip(nodes.into_iter()) {
-                child.inner_graph(node, graph);
+    pub fn softplus(self) -> Value<'a> {
+        Value::from_op(&[self], Operation::Softplus)
+    }
+
+    pub fn elu(self, alpha: f64) -> Value<'a> {
+        Value::from_op(&[self], Operation::Elu(alpha))
+    }
+
+    pub fn silu(self) -> Value<'a> {
+        Value::from_op(&[self], Operation::Silu)
+    }
+}
+
+impl <'a>Value<'a> {
+    /// The same data, but no gradient flows back through it
+    pub fn detach(self) -> Value<'a> {
+        Value::from_op(&[self], Operation::Detach)
+    }
+}
+
+// Gradient overrides
+impl <'a>Value<'a> {
+    /// Build a subgraph from `inputs` with `forward`, but have `backward` call `grad` instead of
+    /// differentiating through it. `grad` takes the inputs' data, the output data and the output's
+    /// gradient and returns one gradient per input, e.g. `|_, _, grad| vec![grad]` for a
+    /// straight-through estimator or `|_, _, grad| vec![-grad]` for gradient reversal.
+    ///
+    /// Every trainable leaf `forward` uses has to be one of `inputs`, otherwise it would silently
+    /// get no gradient. This panics if one isn't.
+    ///
+    /// Forward sweeps, as taken by [`jacobian`](crate::jacobian) when there are more outputs than
+    /// inputs, call `grad` with an upstream gradient of 1 and scale the result, so they are only
+    /// correct when `grad` is linear in it. `backward` and `grad_graph` pass the real gradient.
+    pub fn custom_grad<F, G>(inputs: &[Value<'a>], forward: F, grad: G) -> Value<'a>
+    where F: FnOnce(&[Value<'a>]) -> Value<'a>, G: Fn(&[f64], f64, f64) -> Vec<f64> + 'static {
+        assert!(!inputs.is_empty(), "custom_grad applied to no inputs");
+        let result = forward(inputs);
+        result.assert_no_hidden_leaves(inputs);
+        let mut children = inputs.to_vec();
+        children.push(result);
+        let out = Value::from_op(&children, Operation::CustomGrad(inputs.len()));
+        out.arena.nodes.borrow_mut()[out.id].grad_fn = Some(GradFn(Rc::new(grad)));
+        out
+    }
+
+    // Panic if a trainable leaf feeds this value other than through `inputs`
+    fn assert_no_hidden_leaves(&self, inputs: &[Value<'a>]) {
+        let nodes = self.arena.nodes.borrow();
+        let mut visited: HashSet<usize> = inputs.iter().map(|x| x.id).collect();
+        let mut stack = vec![self.id];
+        while let Some(id) = stack.pop() {
+            if !visited.insert(id) {
+                continue;
             }
+            let node = &nodes[id];
+            assert!(
+                node.operation.is_some() || !node.requires_grad,
+                "trainable leaf {} ({:?}) is used by custom_grad but not passed as one of its inputs", id, node.label,
+            );
+            // Inputs that aren't differentiated don't get a gradient either way
+            if let Some(operation) = node.operation {
+                stack.extend(node.children.iter().enumerate()
+                    .filter(|(slot, _)| operation.differentiable(*slot))
+                    .map(|(_, &child)| child));
+            }
+        }
+    }
+
+    /// Gradient reaching each child of this node given its own gradient `grad`, using the override
+    /// from [`Value::custom_grad`] if there is one. Fails if a user-supplied rule returns the wrong
+    /// number of gradients.
+    pub(crate) fn child_grads(
+        &self,
+        operation: Operation,
+        inputs: &[f64],
+        grad: f64,
+    ) -> Result<Vec<f64>, AutogradError> {
+        let grad_fn = self.arena.nodes.borrow()[self.id].grad_fn.clone();
+        let (expected, mut grads) = match grad_fn {
+            Some(GradFn(grad_fn)) => {
+                let (wrapped, _) = inputs.split_at(inputs.len() - 1);
+                (wrapped.len(), grad_fn(wrapped, self.value(), grad))
+            },
+            None => (inputs.len(), operation.vjp(inputs, self.value(), grad)),
+        };
+        if grads.len() != expected {
+            return Err(AutogradError::GradientCountMismatch {
+                id: self.id,
+                label: self.get_label(),
+                operation,
+                expected,
+                found: grads.len(),
+            });
         }
+        if grads.len() < inputs.len() {
+            // The subgraph's result of a `custom_grad` gets nothing
+            grads.push(0.0);
+        }
+        Ok(grads)
     }
 }
 
 // Backprop
 impl <'a>Value<'a> {
-    // Backprop gradients
-    pub fn backward(&mut self) {
-        if let Some(children) = &mut self.children {
-            // Set child grads
-            match self.operation.unwrap() {
-                Operation::Add => {
-                    for child in children.iter_mut() {
-                        child.grad = self.grad;
-                    }
+    /// All nodes reachable from this value, ordered so every child comes before its parents
+    pub fn topological_order(&self) -> Vec<Value<'a>> {
+        let nodes = self.arena.nodes.borrow();
+        let mut visited = vec![false; nodes.len()];
+        let mut order = vec![];
+        let mut stack = vec![(self.id, false)];
+        while let Some((id, expanded)) = stack.pop() {
+            if expanded {
+                order.push(Value { arena: self.arena, id });
+                continue;
+            }
+            if visited[id] {
+                continue;
+            }
+            visited[id] = true;
+            stack.push((id, true));
+            for &child in nodes[id].children.iter().rev() {
+                if !visited[child] {
+                    stack.push((child, false));
+                }
+            }
+        }
+        order
+    }
+
+    /// Check the graph feeding this value is well formed: every value still exists,
+    /// every operation has the right number of inputs and there are no cycles
+    pub fn validate(&self) -> Result<(), AutogradError> {
+        let nodes = self.arena.nodes.borrow();
+        if self.id >= nodes.len() {
+            return Err(AutogradError::Dangling { id: self.id });
+        }
+        let mut visited = vec![false; nodes.len()];
+        let mut stack = vec![self.id];
+        while let Some(id) = stack.pop() {
+            if visited[id] {
+                continue;
+            }
+            visited[id] = true;
+            let node = &nodes[id];
+            match node.operation {
+                None if !node.children.is_empty() => {
+                    return Err(AutogradError::MissingOperation { id, label: node.label.clone() });
                 },
-                Operation::Mul => {
-                    // Assume there is only 2 children
-                    children[0].grad = self.grad * children[1].value;
-                    children[1].grad = self.grad * children[0].value;
+                Some(operation) if operation.arity() != node.children.len() => {
+                    return Err(AutogradError::ArityMismatch {
+                        id,
+                        label: node.label.clone(),
+                        operation,
+                        expected: operation.arity(),
+                        found: node.children.len(),
+                    });
                 },
-                Operation::Tanh => {
-                    // Assume there is only 1 child
-                    children[0].grad = (1.0 - self.value.powi(2)) * self.grad;
+                _ => {},
+            }
+            for &child in &node.children {
+                // Children are always created before their parents, anything else loops back
+                if child >= id {
+                    return Err(AutogradError::Cycle { id, label: node.label.clone() });
+                }
+                stack.push(child);
+            }
+        }
+        Ok(())
+    }
+
+    /// Like [`Value::backward`], but reports a malformed graph or NaN/infinite data and
+    /// gradients instead of panicking or silently propagating them
+    pub fn try_backward(&self) -> Result<(), AutogradError> {
+        self.validate()?;
+        let order = self.topological_order();
+        if let Some(node) = order.iter().find(|node| !node.value().is_finite()) {
+            return Err(AutogradError::NonFiniteValue { id: node.id, label: node.get_label(), value: node.value() });
+        }
+        self.propagate()?;
+        if let Some(node) = order.iter().find(|node| !node.grad().is_finite()) {
+            return Err(AutogradError::NonFiniteGrad { id: node.id, label: node.get_label(), grad: node.grad() });
+        }
+        Ok(())
+    }
+
+    /// Recompute the data of every node feeding this one from its leaves,
+    /// e.g. after changing a parameter
+    pub fn forward(&self) {
+        for node in self.topological_order() {
+            let Some(operation) = node.operation() else { continue };
+            let inputs: Vec<f64> = node.children().iter().map(|c| c.value()).collect();
+            node.set_value(operation.apply(&inputs));
+        }
+    }
+
+    // Backprop gradients, accumulating into every node reachable from this one that requires them.
+    // Panics if a custom operation returns the wrong number of gradients, see `try_backward`.
+    pub fn backward(&self) {
+        if let Err(error) = self.propagate() {
+            panic!("{}", error);
+        }
+    }
+
+    // The backward pass itself, stopping at the first misbehaving user-supplied rule
+    fn propagate(&self) -> Result<(), AutogradError> {
+        self.set_grad(1.0);
+        let order = self.topological_order();
+        let requires_grad = self.requires_grad_mask(&order);
+        // Nodes the gradient actually flows into, which leaves out anything only feeding a
+        // `detach` or the subgraph of a `custom_grad`
+        let mut reached = vec![false; requires_grad.len()];
+        reached[self.id] = true;
+        for node in order.into_iter().rev() {
+            let Some(operation) = node.operation() else { continue };
+            // Branches without a trainable leaf are skipped entirely
+            if !reached[node.id] || !requires_grad[node.id] {
+                continue;
+            }
+            let children = node.children();
+            let inputs: Vec<f64> = children.iter().map(|c| c.value()).collect();
+            let grads = node.child_grads(operation, &inputs, node.grad())?;
+            for (slot, (child, grad)) in children.iter().zip(grads).enumerate() {
+                if operation.differentiable(slot) && requires_grad[child.id] {
+                    child.add_grad(grad);
+                    reached[child.id] = true;
                 }
             }
+        }
+        Ok(())
+    }
+
+    // Which nodes in `order` lead back to a leaf that requires grad, indexed by id
+    fn requires_grad_mask(&self, order: &[Value<'a>]) -> Vec<bool> {
+        let nodes = self.arena.nodes.borrow();
+        let mut mask = vec![false; nodes.len()];
+        for value in order {
+            let node = &nodes[value.id];
+            mask[value.id] = match node.operation {
+                None => node.requires_grad,
+                Some(operation) => node.children.iter().enumerate()
+                    .any(|(slot, &child)| operation.differentiable(slot) && mask[child]),
+            };
+        }
+        mask
+    }
 
-            // Propagate
-            for child in children {
-                child.backward();
+    /// Gradients of this value with respect to `inputs`, built as new nodes in the graph
+    /// rather than written to `grad`, so they can themselves be differentiated
+    pub fn grad_graph(&self, inputs: &[Value<'a>]) -> Vec<Value<'a>> {
+        let mut adjoints: HashMap<usize, Value<'a>> = HashMap::new();
+        adjoints.insert(self.id, self.arena.constant(1.0));
+        for node in self.topological_order().into_iter().rev() {
+            let (Some(operation), Some(&grad)) = (node.operation(), adjoints.get(&node.id)) else { continue };
+            let children = node.children();
+            let overridden = node.arena.nodes.borrow()[node.id].grad_fn.is_some();
+            let contributions: Vec<Value<'a>> = if overridden {
+                // Overrides only give plain numbers and needn't be linear in the upstream gradient,
+                // so call them with its actual value and add the results as constants
+                let inputs: Vec<f64> = children.iter().map(|c| c.value()).collect();
+                node.child_grads(operation, &inputs, grad.value())
+                    .unwrap_or_else(|error| panic!("{}", error))
+                    .into_iter().map(|g| self.arena.constant(g)).collect()
+            } else {
+                operation.derivatives(&children, node).into_iter().map(|derivative| grad * derivative).collect()
+            };
+            for (slot, (child, contribution)) in children.iter().zip(contributions).enumerate() {
+                if !operation.differentiable(slot) {
+                    continue;
+                }
+                adjoints.entry(child.id)
+                    .and_modify(|adjoint| *adjoint = *adjoint + contribution)
+                    .or_insert(contribution);
             }
         }
+        inputs.iter()
+            .map(|x| adjoints.get(&x.id).copied().unwrap_or_else(|| self.arena.constant(0.0)))
+            .collect()
+    }
+
+    fn add_grad(&self, grad: f64) {
+        self.arena.nodes.borrow_mut()[self.id].grad += grad;
+    }
+
+    // Take a gradient descent step on this value alone, if it is a trainable leaf
+    pub fn apply_grad(&self, learning_rate: f64) {
+        if self.is_trainable() {
+            self.set_value(self.value() - self.grad() * learning_rate);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::assert_close;
+
+    #[test]
+    fn diamond_accumulates() {
+        let arena = Arena::new();
+        let a = arena.value(2.0);
+        let b = arena.value(-3.0);
+        let c = arena.value(7.0);
+        let out = (a * b) + (a * c);
+        out.backward();
+
+        assert_close(out.value(), 8.0);
+        // d/da = b + c, d/db = a, d/dc = a
+        assert_close(a.grad(), 4.0);
+        assert_close(b.grad(), 2.0);
+        assert_close(c.grad(), 2.0);
+    }
+
+    #[test]
+    fn reused_value() {
+        let arena = Arena::new();
+        let a = arena.value(3.0);
+        let b = a * a;
+        let out = (b + a) * b;
+        out.backward();
+
+        // out = a^4 + a^3, d/da = 4a^3 + 3a^2
+        assert_close(out.value(), 108.0);
+        assert_close(a.grad(), 4.0 * 27.0 + 3.0 * 9.0);
+        // d/db = 2b + a
+        assert_close(b.grad(), 21.0);
+    }
+
+    #[test]
+    fn tanh_chain() {
+        let arena = Arena::new();
+        let x = arena.value(0.5);
+        let w = arena.value(-1.5);
+        let h = (x * w).tanh();
+        let out = h + h;
+        out.backward();
+
+        let t = (0.5f64 * -1.5).tanh();
+        assert_close(h.grad(), 2.0);
+        assert_close(x.grad(), 2.0 * (1.0 - t * t) * -1.5);
+        assert_close(w.grad(), 2.0 * (1.0 - t * t) * 0.5);
+    }
+
+    #[test]
+    fn elementwise_ops() {
+        let arena = Arena::new();
+        let a = arena.value(3.0);
+        let b = arena.value(2.0);
+        let out = (a - b) / b + (-a).exp() * a.ln() - a.pow(3.0) + b.sqrt();
+        out.backward();
+
+        let expected = (3.0 - 2.0) / 2.0 + (-3.0f64).exp() * 3.0f64.ln() - 27.0 + 2.0f64.sqrt();
+        assert_close(out.value(), expected);
+        // d/da = 1/b - e^-a ln a + e^-a / a - 3a^2
+        assert_close(a.grad(), 0.5 - (-3.0f64).exp() * 3.0f64.ln() + (-3.0f64).exp() / 3.0 - 27.0);
+        // d/db = -1/b - (a - b)/b^2 + 1/(2 sqrt b)
+        assert_close(b.grad(), -0.5 - 0.25 + 0.5 / 2.0f64.sqrt());
+    }
+
+    #[test]
+    fn zeroth_power_at_zero() {
+        let arena = Arena::new();
+        let x = arena.value(0.0);
+        let out = x.pow(0.0);
+        out.backward();
+
+        assert_eq!(out.value(), 1.0);
+        assert_eq!(x.grad(), 0.0);
+        assert_eq!(out.grad_graph(&[x])[0].value(), 0.0);
+        assert_eq!(crate::jvp(|x| vec![x[0].pow(0.0)], &[0.0], &[1.0]).1, vec![0.0]);
+    }
+
+    #[test]
+    fn abs_subgradient() {
+        let arena = Arena::new();
+        let xs = [arena.value(-2.0), arena.value(0.0), arena.value(3.0)];
+        for x in xs {
+            x.abs().backward();
+        }
+        assert_close(xs[0].grad(), -1.0);
+        // Subgradient of 0 at x = 0
+        assert_close(xs[1].grad(), 0.0);
+        assert_close(xs[2].grad(), 1.0);
     }
 
-    // Apply gradients to values
-    pub fn apply_grad(&mut self, learning_rate: f64) {
-        self.value -= self.grad * learning_rate;
-        
-        if let Some(children) = &mut self.children {
-            for child in children {
-                child.apply_grad(learning_rate);
+    #[test]
+    fn activations_match_finite_differences() {
+        let activations: [fn(Value) -> Value; 7] = [
+            |x| x.relu(),
+            |x| x.leaky_relu(0.1),
+            |x| x.sigmoid(),
+            |x| x.gelu(),
+            |x| x.softplus(),
+            |x| x.elu(1.5),
+            |x| x.silu(),
+        ];
+        for activation in activations {
+            for x in [-2.3, -0.4, 0.7, 1.9] {
+                let arena = Arena::new();
+                let input = arena.value(x);
+                activation(input).backward();
+
+                let h = 1e-6;
+                let numeric = (activation(arena.value(x + h)).value() - activation(arena.value(x - h)).value()) / (2.0 * h);
+                assert!((input.grad() - numeric).abs() < 1e-6, "{} != {}", input.grad(), numeric);
             }
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn relu_subgradient_at_zero() {
+        let arena = Arena::new();
+        let x = arena.value(0.0);
+        x.relu().backward();
+        assert_close(x.grad(), 0.0);
+    }
+
+    #[test]
+    fn scalar_arithmetic() {
+        let arena = Arena::new();
+        let a = arena.value(4.0);
+        let out = (2.0 * a + 1.0) / 3.0 - 1.0 / a - (5.0 - a) * 0.5;
+        out.backward();
+
+        assert_close(out.value(), 3.0 - 0.25 - 0.5);
+        // d/da = 2/3 + 1/a^2 + 0.5
+        assert_close(a.grad(), 2.0 / 3.0 + 1.0 / 16.0 + 0.5);
+        assert_eq!(out.children()[0].children()[1].children()[0].get_label(), "1");
+    }
+
+    #[test]
+    fn scalar_constants_are_not_trained() {
+        let arena = Arena::new();
+        let a = arena.value(4.0);
+        let out = a * 3.0;
+        out.backward();
+
+        let constant = out.children()[1];
+        assert_eq!(constant.get_label(), "3");
+        assert!(!constant.requires_grad() && !constant.is_trainable());
+        assert_eq!(constant.grad(), 0.0);
+        for node in out.topological_order() {
+            node.set_grad(1.0);
+            node.apply_grad(0.1);
+        }
+        assert_eq!(constant.value(), 3.0);
+        assert_eq!(out.value(), 12.0);
+        assert_close(a.value(), 3.9);
+    }
+
+    #[test]
+    fn second_derivative() {
+        let arena = Arena::new();
+        let x = arena.value(2.0);
+        let y = x.pow(3.0) + x.tanh();
+        let dy = y.grad_graph(&[x])[0];
+        let d2y = dy.grad_graph(&[x])[0];
+
+        let t = 2.0f64.tanh();
+        assert_close(dy.value(), 12.0 + 1.0 - t * t);
+        // d/dx (1 - tanh^2) = -2 tanh (1 - tanh^2)
+        assert_close(d2y.value(), 12.0 - 2.0 * t * (1.0 - t * t));
+    }
+
+    #[test]
+    fn grad_graph_matches_backward() {
+        let arena = Arena::new();
+        let x = arena.value(0.3);
+        let y = arena.value(-1.2);
+        let out = (x * y).gelu() + (x / y).exp() - y.softplus() * x.silu() + x.elu(0.5) * y.elu(0.5).sigmoid()
+            + (y - x).abs().sqrt().ln() + y.leaky_relu(0.2) - x.relu();
+        let grads = out.grad_graph(&[x, y]);
+        out.backward();
+
+        assert_close(grads[0].value(), x.grad());
+        assert_close(grads[1].value(), y.grad());
+    }
+
+    #[test]
+    fn hessian_vector_product() {
+        let arena = Arena::new();
+        let x = arena.value(1.5);
+        let y = arena.value(-0.5);
+        // f = x^2 y + y^3, H = [[2y, 2x], [2x, 6y]]
+        let f = x * x * y + y.pow(3.0);
+        let g = f.grad_graph(&[x, y]);
+        let v = [2.0, 3.0];
+        let hv = (g[0] * v[0] + g[1] * v[1]).grad_graph(&[x, y]);
+
+        assert_close(hv[0].value(), 2.0 * -0.5 * 2.0 + 2.0 * 1.5 * 3.0);
+        assert_close(hv[1].value(), 2.0 * 1.5 * 2.0 + 6.0 * -0.5 * 3.0);
+    }
+
+    #[test]
+    fn gradient_penalty() {
+        let arena = Arena::new();
+        let w = arena.value(3.0);
+        let x = arena.value(0.5);
+        // penalty = (d(wx)/dx - 1)^2 = (w - 1)^2
+        let dx = (w * x).grad_graph(&[x])[0];
+        let penalty = (dx - 1.0).pow(2.0);
+        penalty.backward();
+
+        assert_close(penalty.value(), 4.0);
+        assert_close(w.grad(), 4.0);
+    }
+
+    #[test]
+    fn unreachable_gradient_is_zero() {
+        let arena = Arena::new();
+        let x = arena.value(1.0);
+        let y = arena.value(2.0);
+        assert_close((x * 2.0).grad_graph(&[y])[0].value(), 0.0);
+    }
+
+    #[test]
+    fn forward_recomputes() {
+        let arena = Arena::new();
+        let a = arena.value(2.0);
+        let b = a * a + 1.0;
+        let out = b.ln();
+        a.set_value(3.0);
+        out.forward();
+
+        assert_close(b.value(), 10.0);
+        assert_close(out.value(), 10.0f64.ln());
+    }
+
+    #[test]
+    fn try_backward_reports_errors() {
+        let arena = Arena::new();
+        let x = arena.value(2.0).label("x");
+        let out = (x * 3.0).ln();
+        assert_eq!(out.try_backward(), Ok(()));
+        assert_close(x.grad(), 0.5);
+
+        let nan = (arena.value(f64::NAN).label("nan") + x).label("sum");
+        assert!(matches!(nan.try_backward(), Err(AutogradError::NonFiniteValue { id, .. }) if id == nan.children()[0].id));
+
+        let zero = arena.value(0.0).label("zero");
+        let inf = zero.sqrt().label("sqrt");
+        assert_eq!(
+            inf.try_backward(),
+            Err(AutogradError::NonFiniteGrad { id: zero.id, label: "zero".to_string(), grad: f64::INFINITY }),
+        );
+    }
+
+    #[test]
+    fn validate_reports_malformed_graphs() {
+        let arena = Arena::new();
+        let x = arena.value(1.0);
+        let y = arena.value(2.0);
+        let bad_arity = arena.push(1.0, vec![x.id], Some(Operation::Mul)).label("bad");
+        let missing = arena.push(1.0, vec![x.id, y.id], None);
+        let cyclic = arena.push(1.0, vec![x.id], Some(Operation::Exp));
+        arena.nodes.borrow_mut()[cyclic.id].children = vec![cyclic.id];
+
+        assert_eq!(
+            bad_arity.validate(),
+            Err(AutogradError::ArityMismatch { id: bad_arity.id, label: "bad".to_string(), operation: Operation::Mul, expected: 2, found: 1 }),
+        );
+        assert!(matches!(bad_arity.try_backward(), Err(AutogradError::ArityMismatch { .. })));
+        assert!(matches!(missing.validate(), Err(AutogradError::MissingOperation { .. })));
+        assert!(matches!(cyclic.validate(), Err(AutogradError::Cycle { .. })));
+        assert!(matches!(bad_arity.try_graph(), Err(AutogradError::ArityMismatch { .. })));
+
+        arena.truncate(2);
+        assert_eq!(cyclic.validate(), Err(AutogradError::Dangling { id: cyclic.id }));
+        assert_eq!((x + y).validate(), Ok(()));
+    }
+
+    #[test]
+    fn operation_labels() {
+        assert_eq!(Operation::Mul.to_string(), "*");
+        assert_eq!(Operation::Sub.to_string(), "-");
+        assert_eq!(Operation::Pow(2.0).to_string(), "^2");
+    }
+
+    #[test]
+    #[should_panic(expected = "values belong to different arenas")]
+    fn mixing_arenas_panics() {
+        let (a1, a2) = (Arena::new(), Arena::new());
+        let _ = a1.value(1.0) + a2.value(9.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "values belong to different arenas")]
+    fn apply_with_wrong_arity_checks_arenas() {
+        let (a1, a2) = (Arena::new(), Arena::new());
+        let y = a2.value(9.0).exp();
+        let _ = Value::apply(Operation::Exp, &[a1.value(1.0), y]);
+    }
+
+    #[test]
+    fn topological_order_visits_once() {
+        let arena = Arena::new();
+        let a = arena.value(1.0);
+        let b = a + a;
+        let c = b * a;
+        let order = c.topological_order();
+        assert_eq!(order, vec![a, b, c]);
+    }
+
+    struct Round;
+
+    impl crate::CustomOp for Round {
+        fn name(&self) -> String {
+            "round".to_string()
+        }
+
+        fn arity(&self) -> usize {
+            1
+        }
+
+        fn forward(&self, inputs: &[f64]) -> f64 {
+            inputs[0].round()
+        }
+
+        fn backward(&self, inputs: &[f64], _output: f64, _grad: f64) -> Vec<f64> {
+            vec![0.0; inputs.len()]
+        }
+    }
+
+    #[test]
+    fn straight_through_estimator() {
+        let round = crate::register_op(Round);
+        let arena = Arena::new();
+        let x = arena.value(1.3);
+        let quantized = Value::custom_grad(&[x], |x| Value::apply(round, x), |_, _, grad| vec![grad]);
+        let out = quantized * 3.0;
+        out.backward();
+
+        assert_close(out.value(), 3.0);
+        // Rounding alone would give no gradient at all
+        assert_close(x.grad(), 3.0);
+        assert_eq!(Value::apply(round, &[x]).grad_graph(&[x])[0].value(), 0.0);
+        assert_close(out.grad_graph(&[x])[0].value(), 3.0);
+
+        x.set_value(2.6);
+        out.forward();
+        assert_close(out.value(), 9.0);
+    }
+
+    #[test]
+    fn gradient_reversal() {
+        let arena = Arena::new();
+        let x = arena.value(2.0);
+        let w = arena.value(0.5);
+        let reversed = |x: &[f64], _, grad: f64| vec![-x[1] * grad, -x[0] * grad];
+        let out = Value::custom_grad(&[x, w], |x| x[0] * x[1], reversed).tanh();
+        out.backward();
+
+        let t = 1.0f64.tanh();
+        assert_close(out.value(), t);
+        assert_close(x.grad(), -(1.0 - t * t) * 0.5);
+        assert_close(w.grad(), -(1.0 - t * t) * 2.0);
+        assert_eq!(out.children()[0].operation(), Some(Operation::CustomGrad(2)));
+    }
+
+    #[test]
+    fn custom_grad_skips_wrapped_subgraph() {
+        let arena = Arena::new();
+        let x = arena.value(0.0);
+        // sqrt has an infinite derivative at 0, which must not leak through as 0 * inf
+        let out = Value::custom_grad(&[x], |x| x[0].sqrt(), |_, _, grad| vec![grad]);
+        out.backward();
+
+        assert_eq!(x.grad(), 1.0);
+        assert_eq!(out.children()[1].grad(), 0.0);
+        assert_eq!(out.grad_graph(&[x])[0].value(), 1.0);
+        // Two outputs of one input take the forward sweep
+        let jacobian = crate::jacobian(|x| {
+            let out = Value::custom_grad(x, |x| x[0].sqrt(), |_, _, grad| vec![grad]);
+            vec![out, out]
+        }, &[0.0]);
+        assert_eq!(jacobian, vec![vec![1.0], vec![1.0]]);
+    }
+
+    #[test]
+    fn grad_graph_passes_upstream_gradient_to_override() {
+        let arena = Arena::new();
+        let x = arena.value(2.0);
+        let clipped = Value::custom_grad(&[x], |x| x[0] * 1.0, |_, _, grad| vec![grad.clamp(-1.0, 1.0)]);
+        let out = clipped * 5.0;
+        out.backward();
+
+        assert_eq!(x.grad(), 1.0);
+        assert_eq!(out.grad_graph(&[x])[0].value(), 1.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "not passed as one of its inputs")]
+    fn custom_grad_rejects_hidden_leaves() {
+        let arena = Arena::new();
+        let x = arena.value(2.0);
+        let w = arena.value(0.5);
+        Value::custom_grad(&[x], |x| x[0] * w, |_, _, grad| vec![grad]);
+    }
+
+    #[test]
+    fn detach_blocks_gradient() {
+        let arena = Arena::new();
+        let x = arena.value(3.0);
+        let y = arena.value(2.0);
+        let out = x * (x * y).detach() + y;
+        out.backward();
+
+        assert_close(out.value(), 20.0);
+        assert_close(x.grad(), 6.0);
+        assert_close(y.grad(), 1.0);
+        assert!(!(x * y).detach().requires_grad());
+
+        y.set_value(1.0);
+        out.forward();
+        assert_close(out.value(), 10.0);
+    }
+
+    #[test]
+    fn backward_prunes_untrainable_branches() {
+        let arena = Arena::new();
+        let x = arena.value(0.5);
+        let frozen = arena.value(2.0);
+        frozen.set_requires_grad(false);
+        let c = arena.constant(4.0);
+        let branch = (frozen * c).exp();
+        let out = x * branch;
+        out.backward();
+
+        assert!(x.requires_grad() && out.requires_grad());
+        assert!(x.is_trainable() && !frozen.is_trainable() && !out.is_trainable());
+        assert!(!branch.requires_grad() && !c.requires_grad());
+        assert_close(x.grad(), 8.0f64.exp());
+        assert_eq!((branch.grad(), frozen.grad(), c.grad()), (0.0, 0.0, 0.0));
+
+        frozen.set_grad(1.0);
+        frozen.apply_grad(0.1);
+        assert_eq!(frozen.value(), 2.0);
+    }
+}
@@ -0,0 +1,172 @@
+use micrograd::*;
+use proptest::prelude::*;
+
+const INPUTS: usize = 3;
+
+// Random expression over the inputs, built from every `Operation`
+#[derive(Debug, Clone)]
+enum Expr {
+    Input(usize),
+    Unary(Operation, Box<Expr>),
+    Binary(Operation, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    fn eval<S: Scalar>(&self, inputs: &[S]) -> S {
+        match self {
+            Expr::Input(i) => inputs[*i],
+            Expr::Unary(operation, a) => {
+                let x = a.eval(inputs);
+                match operation {
+                    Operation::Neg => -x,
+                    Operation::Pow(exponent) => x.pow(*exponent),
+                    Operation::Exp => x.exp(),
+                    Operation::Ln => x.ln(),
+                    Operation::Sqrt => x.sqrt(),
+                    Operation::Abs => x.abs(),
+                    Operation::Tanh => x.tanh(),
+                    Operation::Relu => x.relu(),
+                    Operation::LeakyRelu(slope) => x.leaky_relu(*slope),
+                    Operation::Sigmoid => x.sigmoid(),
+                    Operation::Gelu => x.gelu(),
+                    Operation::Softplus => x.softplus(),
+                    Operation::Elu(alpha) => x.elu(*alpha),
+                    Operation::Silu => x.silu(),
+                    _ => unreachable!("{} is not unary", operation),
+                }
+            },
+            Expr::Binary(operation, a, b) => {
+                let (x, y) = (a.eval(inputs), b.eval(inputs));
+                match operation {
+                    Operation::Add => x + y,
+                    Operation::Sub => x - y,
+                    Operation::Mul => x * y,
+                    Operation::Div => x / y,
+                    _ => unreachable!("{} is not binary", operation),
+                }
+            },
+        }
+    }
+}
+
+fn unary_operation() -> impl Strategy<Value = Operation> {
+    prop_oneof![
+        Just(Operation::Neg),
+        prop_oneof![
+            prop::sample::select(vec![-2.0, -1.0, -0.5, 0.0, 0.5, 1.0, 2.0, 3.0]),
+            -3.0..3.0,
+        ].prop_map(Operation::Pow),
+        Just(Operation::Exp),
+        Just(Operation::Ln),
+        Just(Operation::Sqrt),
+        Just(Operation::Abs),
+        Just(Operation::Tanh),
+        Just(Operation::Relu),
+        (0.01..0.5).prop_map(Operation::LeakyRelu),
+        Just(Operation::Sigmoid),
+        Just(Operation::Gelu),
+        Just(Operation::Softplus),
+        (0.1..2.0).prop_map(Operation::Elu),
+        Just(Operation::Silu),
+    ]
+}
+
+fn binary_operation() -> impl Strategy<Value = Operation> {
+    prop::sample::select(vec![Operation::Add, Operation::Sub, Operation::Mul, Operation::Div])
+}
+
+fn expr() -> impl Strategy<Value = Expr> {
+    (0..INPUTS).prop_map(Expr::Input).prop_recursive(5, 32, 2, |inner| {
+        prop_oneof![
+            (unary_operation(), inner.clone()).prop_map(|(op, a)| Expr::Unary(op, Box::new(a))),
+            (binary_operation(), inner.clone(), inner).prop_map(|(op, a, b)| Expr::Binary(op, Box::new(a), Box::new(b))),
+        ]
+    })
+}
+
+fn inputs() -> impl Strategy<Value = Vec<f64>> {
+    prop::collection::vec(-2.0..2.0, INPUTS)
+}
+
+fn close(a: f64, b: f64, tolerance: f64) -> bool {
+    (a - b).abs() <= tolerance * (1.0 + a.abs().max(b.abs()))
+}
+
+// How far inputs have to stay from a kink, pole or the edge of an operation's domain
+const MARGIN: f64 = 1e-2;
+
+/// Skip samples that overflow, leave an operation's domain or sit too close to a kink for finite differences
+fn well_behaved(output: Value) -> bool {
+    output.topological_order().iter().all(|node| {
+        let inputs: Vec<f64> = node.children().iter().map(|c| c.value()).collect();
+        let in_domain = match node.operation() {
+            None => true,
+            Some(Operation::Abs | Operation::Relu | Operation::LeakyRelu(_) | Operation::Elu(_)) => inputs[0].abs() > MARGIN,
+            Some(Operation::Ln | Operation::Sqrt) => inputs[0] > MARGIN,
+            Some(Operation::Pow(exponent)) if exponent.fract() != 0.0 => inputs[0] > MARGIN,
+            Some(Operation::Pow(exponent)) if exponent < 0.0 => inputs[0].abs() > MARGIN,
+            Some(Operation::Div) => inputs[1].abs() > MARGIN,
+            Some(_) => true,
+        };
+        in_domain && node.value().is_finite() && node.value().abs() < 1e6
+    })
+}
+
+proptest! {
+    // Random inputs often leave the domain of `ln`, `sqrt`, fractional powers and division
+    #![proptest_config(ProptestConfig { max_global_rejects: 1 << 20, ..ProptestConfig::default() })]
+
+    #[test]
+    fn backward_matches_finite_differences(expr in expr(), x in inputs()) {
+        let arena = Arena::new();
+        let inputs: Vec<Value> = x.iter().map(|x| arena.value(*x)).collect();
+        let output = expr.eval(&inputs);
+        prop_assume!(well_behaved(output));
+
+        let report = gradcheck(output, GradcheckOptions { eps: 1e-6, atol: 1e-4, rtol: 1e-3 });
+        prop_assert!(report.passed(), "{:?}\n{}", expr, report);
+    }
+
+    #[test]
+    fn backward_matches_forward_mode(expr in expr(), x in inputs()) {
+        let arena = Arena::new();
+        let inputs: Vec<Value> = x.iter().map(|x| arena.value(*x)).collect();
+        let output = expr.eval(&inputs);
+        prop_assume!(well_behaved(output));
+        output.backward();
+
+        for (i, input) in inputs.iter().enumerate() {
+            let direction: Vec<f64> = (0..INPUTS).map(|j| if i == j { 1.0 } else { 0.0 }).collect();
+            let (values, tangents) = jvp(|duals| vec![expr.eval(duals)], &x, &direction);
+            prop_assert!(close(values[0], output.value(), 1e-12));
+            prop_assert!(close(tangents[0], input.grad(), 1e-9), "{:?}: {} != {}", expr, tangents[0], input.grad());
+        }
+    }
+
+    #[test]
+    fn grad_graph_matches_backward(expr in expr(), x in inputs()) {
+        let arena = Arena::new();
+        let inputs: Vec<Value> = x.iter().map(|x| arena.value(*x)).collect();
+        let output = expr.eval(&inputs);
+        prop_assume!(well_behaved(output));
+        let grads = output.grad_graph(&inputs);
+        output.backward();
+
+        for (grad, input) in grads.iter().zip(&inputs) {
+            prop_assert!(close(grad.value(), input.grad(), 1e-9), "{:?}: {} != {}", expr, grad.value(), input.grad());
+        }
+    }
+
+    #[test]
+    fn rules_match_arity(expr in expr(), x in inputs()) {
+        let arena = Arena::new();
+        let inputs: Vec<Value> = x.iter().map(|x| arena.value(*x)).collect();
+        let output = expr.eval(&inputs);
+
+        for node in output.topological_order() {
+            let Some(operation) = node.operation() else { continue };
+            let children: Vec<f64> = node.children().iter().map(|c| c.value()).collect();
+            prop_assert_eq!(operation.derivatives(&children, node.value()).len(), children.len());
+        }
+    }
+}